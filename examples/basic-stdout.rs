@@ -166,7 +166,7 @@ async fn update_tab_level(
 ) -> Result<()> {
     // Use appropriate module patterns for different tabs
     let module_patterns = match tab {
-        "database" => vec!["*database*".to_string()],
+        "database" => vec!["**database**".to_string()],
         _ => vec!["*".to_string()], // Default pattern for Main, Errors, Warnings, etc.
     };
 
@@ -194,7 +194,7 @@ async fn main() -> Result<()> {
         ("main", Matcher::debug().all_modules()),
         (
             "database",
-            Matcher::debug().include().module_pattern("*database*"),
+            Matcher::debug().include().module_pattern("**database**"),
         ),
         ("errors", Matcher::error().all_modules()),
         ("warnings", Matcher::warn().all_modules()),
@@ -0,0 +1,148 @@
+// src/callsite_filter.rs
+use std::{
+    collections::HashMap,
+    sync::{Mutex, RwLock},
+};
+use tracing::{Dispatch, Metadata, dispatcher::WeakDispatch, level_filters::LevelFilter};
+
+use crate::{Matcher, MatcherSet};
+
+// Union of every tab's matchers, reduced to what callsite metadata can answer.
+// The dispatcher rebuilds it whenever tabs or callbacks change, and the subscriber
+// consults it so callsites no tab could capture never produce a `TraceData`.
+#[derive(Debug)]
+pub(crate) struct CallsiteFilter {
+    state: RwLock<FilterState>,
+    // Dispatches wrapping subscribers that consult this filter
+    dispatches: Mutex<Vec<WeakDispatch>>,
+}
+
+#[derive(Debug, Default)]
+struct FilterState {
+    matchers: Vec<Matcher>,
+    accept_all: bool,
+}
+
+impl CallsiteFilter {
+    /// Filter that lets everything through, for subscribers not tied to a tracer
+    pub(crate) fn accept_all() -> Self {
+        Self {
+            state: RwLock::new(FilterState {
+                matchers: Vec::new(),
+                accept_all: true,
+            }),
+            dispatches: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Filter that only lets through what the given tabs could capture
+    pub(crate) fn from_tabs(tabs: &HashMap<String, MatcherSet>) -> Self {
+        Self {
+            state: RwLock::new(FilterState::from_tabs(tabs, false, false)),
+            dispatches: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Recompute the union and have `tracing` re-ask every callsite for its interest.
+    /// Exclude matchers only matter while a silenced callback wants to see what they
+    /// silence, and a dropped callback needs every event.
+    pub(crate) fn rebuild(
+        &self,
+        tabs: &HashMap<String, MatcherSet>,
+        with_silenced: bool,
+        with_dropped: bool,
+    ) {
+        let state = FilterState::from_tabs(tabs, with_silenced, with_dropped);
+        *self.state.write().unwrap() = state;
+
+        // While only one dispatch exists, `tracing` rebuilds against the calling thread's
+        // default, which is not ours when the subscriber was installed as a scoped default
+        let dispatch = self
+            .dispatches
+            .lock()
+            .unwrap()
+            .iter()
+            .find_map(WeakDispatch::upgrade);
+        match dispatch {
+            Some(dispatch) => tracing::dispatcher::with_default(
+                &dispatch,
+                tracing_core::callsite::rebuild_interest_cache,
+            ),
+            None => tracing_core::callsite::rebuild_interest_cache(),
+        }
+    }
+
+    /// Remember a dispatch consulting this filter, for rebuilding its interest later
+    pub(crate) fn register_dispatch(&self, dispatch: &Dispatch) {
+        let mut dispatches = self.dispatches.lock().unwrap();
+        dispatches.retain(|weak| weak.upgrade().is_some());
+        dispatches.push(dispatch.downgrade());
+    }
+
+    /// Whether a callsite could produce anything a tab captures. Spans are enabled at
+    /// every level while any tab can capture something: a span's level says nothing
+    /// about the events inside it, which carry its name and fields either way.
+    pub(crate) fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        let state = self.state.read().unwrap();
+        if state.accept_all {
+            return true;
+        }
+
+        if metadata.is_span() {
+            !state.matchers.is_empty()
+        } else {
+            state
+                .matchers
+                .iter()
+                .any(|matcher| matcher.matches_metadata(metadata))
+        }
+    }
+
+    /// Most verbose level any tab can capture an event at, for sources without spans
+    #[cfg(feature = "log")]
+    pub(crate) fn max_event_level(&self) -> LevelFilter {
+        let state = self.state.read().unwrap();
+        if state.accept_all {
+            return LevelFilter::TRACE;
+        }
+
+        state
+            .matchers
+            .iter()
+            .map(|matcher| LevelFilter::from_level(matcher.max_level()))
+            .max()
+            .unwrap_or(LevelFilter::OFF)
+    }
+
+    /// Most verbose level a callsite may need. Spans of any level can hold captured
+    /// events, so this only narrows to `OFF` when nothing can be captured; events are
+    /// still filtered per callsite by `enabled`.
+    pub(crate) fn max_level_hint(&self) -> LevelFilter {
+        let state = self.state.read().unwrap();
+        if state.accept_all || !state.matchers.is_empty() {
+            LevelFilter::TRACE
+        } else {
+            LevelFilter::OFF
+        }
+    }
+}
+
+impl FilterState {
+    fn from_tabs(
+        tabs: &HashMap<String, MatcherSet>,
+        with_silenced: bool,
+        with_dropped: bool,
+    ) -> Self {
+        let matchers = tabs
+            .values()
+            .flat_map(MatcherSet::iter_matchers)
+            .filter(|matcher| matcher.include || with_silenced)
+            .cloned()
+            .collect();
+
+        Self {
+            matchers,
+            accept_all: with_dropped,
+        }
+    }
+}
@@ -0,0 +1,310 @@
+// src/env_filter.rs
+use tracing::Level;
+
+use crate::{FilterParseError, LevelMatch, Matcher, MatcherSet};
+
+// One `target[span{field=value}]=level` directive. `level` is `None` for `off`.
+#[derive(Debug)]
+struct Directive {
+    target: Option<String>,
+    span: Option<String>,
+    fields: Vec<String>,
+    level: Option<Level>,
+}
+
+impl Directive {
+    // Static directives apply by target alone, the rest depend on the current span
+    fn is_static(&self) -> bool {
+        self.span.is_none() && self.fields.is_empty()
+    }
+
+    // Whether this static directive covers every target `other` covers, and more
+    fn is_broader_than(&self, other: &Directive) -> bool {
+        match (&self.target, &other.target) {
+            (None, Some(_)) => true,
+            (Some(broad), Some(narrow)) => narrow.len() > broad.len() && narrow.starts_with(broad),
+            _ => false,
+        }
+    }
+
+    fn include_matcher(&self, level: Level) -> Matcher {
+        let mut matcher = Matcher::new(level);
+        if let Some(target) = &self.target {
+            matcher = matcher.target_pattern(format!("{target}**"));
+        }
+        if self.fields.is_empty() {
+            if let Some(span) = &self.span {
+                matcher = matcher.ancestor_pattern(span);
+            }
+            return matcher;
+        }
+
+        // Fields are checked on the named span itself, not on what inner spans override
+        let span = self.span.as_deref().unwrap_or("*");
+        matcher.span_field_pattern(format!("{span}{{{}}}", self.fields.join(", ")))
+    }
+}
+
+impl MatcherSet {
+    /// Translate `RUST_LOG`-style directives, e.g. `my_app=debug,hyper=warn,[request{id=5}]=trace`,
+    /// into a first-match set. Targets match by prefix like `EnvFilter`, and the most
+    /// specific target directive decides, so matchers run from the longest target down
+    /// to the global default. Span directives come first and match events inside a span
+    /// of that name, with span fields checked against that span's own fields; events
+    /// they don't enable fall through to the target directives.
+    pub fn from_env_filter(filter: &str) -> Result<Self, FilterParseError> {
+        let mut directives = split_directives(filter)?
+            .into_iter()
+            .map(|(position, text)| parse_directive(position, text))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Switched-off spans, then the other span directives, then targets from the
+        // longest down to the global default
+        directives.sort_by_key(|directive| {
+            (
+                directive.is_static(),
+                !directive.is_static() && directive.level.is_some(),
+                std::cmp::Reverse(directive.target.as_ref().map(String::len)),
+            )
+        });
+
+        let mut set = MatcherSet::empty().first_match();
+        for directive in &directives {
+            if !directive.is_static() {
+                match directive.level {
+                    Some(level) => set.add_matcher(directive.include_matcher(level)),
+                    // Spans switched off win over everything else
+                    None => set.add_matcher(directive.include_matcher(Level::TRACE).exclude()),
+                }
+                continue;
+            }
+
+            if let Some(level) = directive.level {
+                set.add_matcher(directive.include_matcher(level));
+            }
+
+            // Keep the levels this directive leaves out from reaching a broader, more
+            // verbose one further down
+            let Some(excluded) = more_verbose_than(directive.level) else {
+                continue;
+            };
+            let widened = directives.iter().any(|broader| {
+                broader.is_static()
+                    && broader.is_broader_than(directive)
+                    && more_verbose(broader.level, directive.level)
+            });
+            if widened {
+                set.add_matcher(
+                    directive
+                        .include_matcher(excluded)
+                        .level_match(LevelMatch::AtMost)
+                        .exclude(),
+                );
+            }
+        }
+
+        Ok(set)
+    }
+}
+
+// The most severe level a directive at `level` does not enable
+fn more_verbose_than(level: Option<Level>) -> Option<Level> {
+    match level {
+        None => Some(Level::ERROR),
+        Some(Level::ERROR) => Some(Level::WARN),
+        Some(Level::WARN) => Some(Level::INFO),
+        Some(Level::INFO) => Some(Level::DEBUG),
+        Some(Level::DEBUG) => Some(Level::TRACE),
+        Some(_) => None,
+    }
+}
+
+// Whether `level` enables anything `than` does not
+fn more_verbose(level: Option<Level>, than: Option<Level>) -> bool {
+    match (level, than) {
+        (Some(level), Some(than)) => level > than,
+        (Some(_), None) => true,
+        (None, _) => false,
+    }
+}
+
+// Split on the commas between directives, leaving those inside `[...]` alone
+fn split_directives(filter: &str) -> Result<Vec<(usize, &str)>, FilterParseError> {
+    let mut directives = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut opened_at = 0;
+    for (index, c) in filter.char_indices() {
+        match c {
+            '[' | '{' => {
+                if depth == 0 {
+                    opened_at = index;
+                }
+                depth += 1;
+            }
+            ']' | '}' => {
+                depth = depth.checked_sub(1).ok_or_else(|| FilterParseError {
+                    position: index,
+                    message: format!("unmatched `{c}`"),
+                })?;
+            }
+            ',' if depth == 0 => {
+                directives.push((start, &filter[start..index]));
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    if depth > 0 {
+        return Err(FilterParseError {
+            position: opened_at,
+            message: "unclosed `[` or `{`".to_string(),
+        });
+    }
+    directives.push((start, &filter[start..]));
+
+    // Skip empty directives, as `EnvFilter` does for trailing commas
+    Ok(directives
+        .into_iter()
+        .filter_map(|(position, text)| {
+            let trimmed = text.trim_start();
+            let position = position + text.len() - trimmed.len();
+            let trimmed = trimmed.trim_end();
+            (!trimmed.is_empty()).then_some((position, trimmed))
+        })
+        .collect())
+}
+
+fn parse_directive(position: usize, text: &str) -> Result<Directive, FilterParseError> {
+    let error = |offset: usize, message: String| FilterParseError {
+        position: position + offset,
+        message,
+    };
+
+    // The level follows the last `=` outside the span section
+    let span_end = text.rfind(']').map_or(0, |index| index + 1);
+    let (selector, level) = match text[span_end..].find('=') {
+        Some(index) => {
+            let index = span_end + index;
+            let level = parse_level(&text[index + 1..]).ok_or_else(|| {
+                error(index + 1, format!("invalid level `{}`", &text[index + 1..]))
+            })?;
+            (&text[..index], level)
+        }
+        // A lone level sets the default, anything else is a target enabled at every level
+        None => match parse_level(text) {
+            Some(level) => {
+                return Ok(Directive {
+                    target: None,
+                    span: None,
+                    fields: Vec::new(),
+                    level,
+                });
+            }
+            None => (text, Some(Level::TRACE)),
+        },
+    };
+
+    let (target, span_section) = match selector.find('[') {
+        Some(index) => (&selector[..index], Some((index, &selector[index..]))),
+        None => (selector, None),
+    };
+    let target = target.trim();
+    if target.is_empty() && span_section.is_none() {
+        return Err(error(0, "expected a target or span before `=`".to_string()));
+    }
+    if let Some(offset) = target.find(|c: char| c.is_whitespace() || "]{}=".contains(c)) {
+        return Err(error(
+            offset,
+            format!("unexpected `{}` in target", &target[offset..=offset]),
+        ));
+    }
+
+    let (span, fields) = match span_section {
+        Some((offset, section)) => parse_span_section(section)
+            .map_err(|(inner, message)| error(offset + inner, message))?,
+        None => (None, Vec::new()),
+    };
+
+    Ok(Directive {
+        target: (!target.is_empty()).then(|| target.to_string()),
+        span,
+        fields,
+        level,
+    })
+}
+
+// Parse `[span{field=value,...}]` into a span name and field patterns. Errors carry the
+// offset into the section.
+fn parse_span_section(section: &str) -> Result<(Option<String>, Vec<String>), (usize, String)> {
+    let Some(inner) = section
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+    else {
+        return Err((0, "expected `[span{fields}]`".to_string()));
+    };
+
+    let (name, fields) = match inner.find('{') {
+        Some(index) => {
+            let Some(fields) = inner[index + 1..].strip_suffix('}') else {
+                return Err((
+                    index + 1,
+                    "expected `}` to close the span fields".to_string(),
+                ));
+            };
+            (&inner[..index], Some((index + 2, fields)))
+        }
+        None => (inner, None),
+    };
+    let name = name.trim();
+    if let Some(offset) = name.find(['[', ']', '{', '}', '=']) {
+        return Err((
+            offset + 1,
+            format!("unexpected `{}` in span name", &name[offset..=offset]),
+        ));
+    }
+
+    let mut patterns = Vec::new();
+    if let Some((offset, fields)) = fields {
+        let mut field_offset = offset;
+        for field in fields.split(',') {
+            let pattern = match field.split_once('=') {
+                Some((name, value)) => {
+                    let value = value.trim();
+                    let value = value
+                        .strip_prefix('"')
+                        .and_then(|value| value.strip_suffix('"'))
+                        .unwrap_or(value);
+                    format!("{} = {value}", name.trim())
+                }
+                None => field.trim().to_string(),
+            };
+            if pattern.is_empty() || pattern.starts_with(' ') {
+                return Err((field_offset, "expected a field name".to_string()));
+            }
+            if pattern.starts_with(['"', '{', '[']) || field.contains("=~") {
+                return Err((
+                    field_offset,
+                    format!("unsupported span field filter `{}`", field.trim()),
+                ));
+            }
+            patterns.push(pattern);
+            field_offset += field.len() + 1;
+        }
+    }
+
+    Ok(((!name.is_empty()).then(|| name.to_string()), patterns))
+}
+
+// `off` is `Some(None)`, a level `Some(Some(level))`
+fn parse_level(text: &str) -> Option<Option<Level>> {
+    let level = match text.trim().to_lowercase().as_str() {
+        "off" | "0" => None,
+        "error" | "1" => Some(Level::ERROR),
+        "warn" | "2" => Some(Level::WARN),
+        "info" | "3" => Some(Level::INFO),
+        "debug" | "4" => Some(Level::DEBUG),
+        "trace" | "5" => Some(Level::TRACE),
+        _ => return None,
+    };
+    Some(level)
+}
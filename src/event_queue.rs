@@ -0,0 +1,297 @@
+// src/event_queue.rs
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::Cell,
+    collections::VecDeque,
+    sync::{
+        Arc, Condvar, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+use tokio::{
+    runtime::{Handle, RuntimeFlavor},
+    sync::{Notify, mpsc},
+};
+use tracing::Level;
+
+use crate::TraceEvent;
+
+// What to do with an event that arrives while the queue is full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum OverflowPolicy {
+    /// Discard the incoming event
+    #[default]
+    DropNewest,
+    /// Discard the oldest queued event to make room
+    DropOldest,
+    /// Discard the most verbose queued event (oldest first), or the incoming event
+    /// if nothing queued is more verbose than it
+    DropLowestLevel,
+    /// Block the emitting thread until the dispatcher catches up, for at most
+    /// `BLOCK_TIMEOUT` per event. When that runs out the event is dropped like with
+    /// `DropNewest`, and later events are dropped without waiting until the dispatcher
+    /// takes one again. Also drops without waiting on a current-thread runtime and for
+    /// events emitted by the dispatcher itself, such as from a callback.
+    Block,
+}
+
+/// Longest a producer waits for room under `OverflowPolicy::Block`
+pub const BLOCK_TIMEOUT: Duration = Duration::from_secs(1);
+
+// Make room in a full buffer for an incoming event at `level`. Returns whether the
+// incoming event should be kept; either way exactly one event is lost.
+pub(crate) fn make_room<T>(
+    buffer: &mut VecDeque<T>,
+    level: Level,
+    level_of: impl Fn(&T) -> Level,
+    policy: OverflowPolicy,
+) -> bool {
+    match policy {
+        OverflowPolicy::DropNewest | OverflowPolicy::Block => false,
+        OverflowPolicy::DropOldest => {
+            buffer.pop_front();
+            true
+        }
+        OverflowPolicy::DropLowestLevel => {
+            // More verbose levels compare greater, so the lowest level is the maximum
+            let lowest = buffer
+                .iter()
+                .enumerate()
+                .filter(|(_, item)| level_of(item) > level)
+                .max_by(|(i, a), (j, b)| level_of(a).cmp(&level_of(b)).then(j.cmp(i)));
+            match lowest {
+                Some((index, _)) => {
+                    buffer.remove(index);
+                    true
+                }
+                None => false,
+            }
+        }
+    }
+}
+
+// Create a queue between subscribers and the dispatcher, bounded when `capacity` is set
+pub(crate) fn event_queue(
+    capacity: Option<usize>,
+    policy: OverflowPolicy,
+    overflowed: Arc<AtomicU64>,
+) -> (EventSender, EventReceiver) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(QueueState {
+            events: VecDeque::new(),
+            senders: 1,
+            receiver_alive: true,
+            stalled: false,
+        }),
+        capacity,
+        policy,
+        overflowed,
+        event_available: Notify::new(),
+        space_available: Condvar::new(),
+    });
+
+    let sender = EventSender {
+        inner: SenderInner::Queue(QueueSender(shared.clone())),
+    };
+    (sender, EventReceiver(shared))
+}
+
+struct Shared {
+    state: Mutex<QueueState>,
+    capacity: Option<usize>,
+    policy: OverflowPolicy,
+    overflowed: Arc<AtomicU64>,
+    event_available: Notify,
+    space_available: Condvar,
+}
+
+struct QueueState {
+    events: VecDeque<TraceEvent>,
+    senders: usize,
+    receiver_alive: bool,
+    /// A blocked producer gave up waiting, and the dispatcher hasn't taken an event since
+    stalled: bool,
+}
+
+// Sending half used by subscribers and layers to reach the dispatcher
+#[derive(Clone)]
+pub struct EventSender {
+    inner: SenderInner,
+}
+
+#[derive(Clone)]
+enum SenderInner {
+    Channel(mpsc::UnboundedSender<TraceEvent>),
+    Queue(QueueSender),
+}
+
+impl EventSender {
+    /// Queue an event for the dispatcher, handing it back if it was discarded because
+    /// the queue is full or the dispatcher is gone
+    pub fn send(&self, event: TraceEvent) -> Result<(), TraceEvent> {
+        match &self.inner {
+            SenderInner::Channel(sender) => sender.send(event).map_err(|error| error.0),
+            SenderInner::Queue(sender) => sender.send(event),
+        }
+    }
+}
+
+// Plain channels stay usable, e.g. for driving a subscriber directly in tests
+impl From<mpsc::UnboundedSender<TraceEvent>> for EventSender {
+    fn from(sender: mpsc::UnboundedSender<TraceEvent>) -> Self {
+        Self {
+            inner: SenderInner::Channel(sender),
+        }
+    }
+}
+
+struct QueueSender(Arc<Shared>);
+
+impl QueueSender {
+    fn send(&self, event: TraceEvent) -> Result<(), TraceEvent> {
+        let shared = &self.0;
+        let mut state = shared.state.lock().unwrap();
+        if !state.receiver_alive {
+            return Err(event);
+        }
+
+        if let Some(capacity) = shared.capacity
+            && state.events.len() >= capacity
+        {
+            if shared.policy == OverflowPolicy::Block && !state.stalled && can_block() {
+                // On a worker thread the dispatcher may have been woken onto this very
+                // worker, so hand its queued tasks over to another thread while waiting
+                let deadline = Instant::now() + BLOCK_TIMEOUT;
+                state = tokio::task::block_in_place(|| {
+                    while state.receiver_alive && state.events.len() >= capacity {
+                        let remaining = deadline.saturating_duration_since(Instant::now());
+                        if remaining.is_zero() {
+                            // The dispatcher may be waiting on this thread, don't wait again
+                            state.stalled = true;
+                            break;
+                        }
+                        state = shared
+                            .space_available
+                            .wait_timeout(state, remaining)
+                            .unwrap()
+                            .0;
+                    }
+                    state
+                });
+                if !state.receiver_alive {
+                    return Err(event);
+                }
+                if state.events.len() >= capacity {
+                    shared.overflowed.fetch_add(1, Ordering::SeqCst);
+                    return Err(event);
+                }
+            } else {
+                shared.overflowed.fetch_add(1, Ordering::SeqCst);
+                let level = event.level.0;
+                if !make_room(&mut state.events, level, |e| e.level.0, shared.policy) {
+                    return Err(event);
+                }
+            }
+        }
+
+        state.events.push_back(event);
+        drop(state);
+        shared.event_available.notify_one();
+        Ok(())
+    }
+}
+
+impl Clone for QueueSender {
+    fn clone(&self) -> Self {
+        self.0.state.lock().unwrap().senders += 1;
+        Self(self.0.clone())
+    }
+}
+
+impl Drop for QueueSender {
+    fn drop(&mut self) {
+        let mut state = self.0.state.lock().unwrap();
+        state.senders -= 1;
+        if state.senders == 0 {
+            drop(state);
+            // Wake the dispatcher so it notices the queue is closed
+            self.0.event_available.notify_one();
+        }
+    }
+}
+
+thread_local! {
+    static DISPATCHING: Cell<bool> = const { Cell::new(false) };
+}
+
+// Marks the current thread as running dispatcher callbacks until dropped
+pub(crate) struct DispatchingGuard(bool);
+
+impl DispatchingGuard {
+    pub(crate) fn enter() -> Self {
+        Self(DISPATCHING.replace(true))
+    }
+}
+
+impl Drop for DispatchingGuard {
+    fn drop(&mut self) {
+        DISPATCHING.set(self.0);
+    }
+}
+
+// Whether this thread is running dispatcher callbacks
+pub(crate) fn is_dispatching() -> bool {
+    DISPATCHING.get()
+}
+
+// Blocking a current-thread runtime would stop the dispatcher from ever draining, and
+// blocking the dispatcher itself would wait on the very thread that drains
+pub(crate) fn can_block() -> bool {
+    !is_dispatching()
+        && Handle::try_current().map_or(true, |handle| {
+            handle.runtime_flavor() != RuntimeFlavor::CurrentThread
+        })
+}
+
+// Receiving half owned by the dispatcher
+pub(crate) struct EventReceiver(Arc<Shared>);
+
+impl EventReceiver {
+    /// Wait for the next event, or `None` once every sender is gone and the queue is empty
+    pub(crate) async fn recv(&mut self) -> Option<TraceEvent> {
+        loop {
+            {
+                let mut state = self.0.state.lock().unwrap();
+                if let Some(event) = state.events.pop_front() {
+                    state.stalled = false;
+                    drop(state);
+                    self.0.space_available.notify_one();
+                    return Some(event);
+                }
+                if state.senders == 0 {
+                    return None;
+                }
+            }
+            // A notification sent after we released the lock is stored as a permit
+            self.0.event_available.notified().await;
+        }
+    }
+
+    /// Take the next queued event without waiting
+    pub(crate) fn try_recv(&mut self) -> Option<TraceEvent> {
+        let mut state = self.0.state.lock().unwrap();
+        let event = state.events.pop_front()?;
+        state.stalled = false;
+        drop(state);
+        self.0.space_available.notify_one();
+        Some(event)
+    }
+}
+
+impl Drop for EventReceiver {
+    fn drop(&mut self) {
+        self.0.state.lock().unwrap().receiver_alive = false;
+        self.0.space_available.notify_all();
+    }
+}
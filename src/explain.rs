@@ -0,0 +1,150 @@
+// src/explain.rs
+use std::fmt;
+
+use crate::{Matcher, MatcherSet, TraceData};
+
+/// The part of a matcher an event failed to meet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Criterion {
+    Level,
+    Module,
+    File,
+    Span,
+    Ancestor,
+    SpanPath,
+    Target,
+    Thread,
+    Message,
+    /// Index into `Matcher::field_patterns`
+    Field(usize),
+    /// Index into `Matcher::span_field_patterns`
+    SpanField(usize),
+}
+
+impl fmt::Display for Criterion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Criterion::Level => "level",
+            Criterion::Module => "module",
+            Criterion::File => "file",
+            Criterion::Span => "span",
+            Criterion::Ancestor => "ancestor",
+            Criterion::SpanPath => "span_path",
+            Criterion::Target => "target",
+            Criterion::Thread => "thread",
+            Criterion::Message => "message",
+            Criterion::Field(_) => "field",
+            Criterion::SpanField(_) => "span_field",
+        };
+        f.write_str(name)
+    }
+}
+
+/// What a tab does with an event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Verdict {
+    Captured,
+    Silenced,
+    Dropped,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Verdict::Captured => "captured",
+            Verdict::Silenced => "silenced",
+            Verdict::Dropped => "dropped",
+        };
+        f.write_str(name)
+    }
+}
+
+/// How one matcher judged an event
+#[derive(Debug, Clone)]
+pub struct MatcherExplanation {
+    pub matcher: Matcher,
+    /// `None` when the matcher matched
+    pub failed: Option<Criterion>,
+}
+
+impl fmt::Display for MatcherExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` ", self.matcher)?;
+        match self.failed {
+            None => f.write_str("matched"),
+            Some(Criterion::Field(index)) => match self.matcher.field_patterns.get(index) {
+                Some(pattern) => write!(f, "failed on field `{pattern}`"),
+                None => f.write_str("failed on field"),
+            },
+            Some(Criterion::SpanField(index)) => {
+                match self.matcher.span_field_patterns.get(index) {
+                    Some(pattern) => write!(f, "failed on span field `{pattern}`"),
+                    None => f.write_str("failed on span field"),
+                }
+            }
+            Some(criterion) => write!(f, "failed on {criterion}"),
+        }
+    }
+}
+
+/// Why a set captured, silenced or dropped an event
+#[derive(Debug, Clone)]
+pub struct Explanation {
+    pub verdict: Verdict,
+    /// Index into `matchers` of the matcher that decided, unless the event was dropped
+    pub decided_by: Option<usize>,
+    /// Every matcher in the set, in the order they were added
+    pub matchers: Vec<MatcherExplanation>,
+}
+
+impl Explanation {
+    pub fn decisive_matcher(&self) -> Option<&Matcher> {
+        self.decided_by.map(|index| &self.matchers[index].matcher)
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.decisive_matcher() {
+            Some(matcher) => write!(f, "{} by `{matcher}`", self.verdict)?,
+            None if self.matchers.is_empty() => write!(f, "{}, no matchers", self.verdict)?,
+            None => write!(f, "{}, no matcher matched", self.verdict)?,
+        }
+        for matcher in &self.matchers {
+            write!(f, "\n  {matcher}")?;
+        }
+        Ok(())
+    }
+}
+
+impl MatcherSet {
+    /// Judge an event with every matcher, reporting which one decided its fate or,
+    /// for those that did not match, the first criterion that failed
+    pub fn explain(&self, event: &TraceData) -> Explanation {
+        let decisive = self.evaluate(event);
+        let matchers = self.iter_matchers();
+
+        let decided_by = decisive.and_then(|decisive| {
+            matchers
+                .iter()
+                .position(|matcher| std::ptr::eq(*matcher, decisive))
+        });
+        let verdict = match decisive {
+            Some(matcher) if matcher.include => Verdict::Captured,
+            Some(_) => Verdict::Silenced,
+            None => Verdict::Dropped,
+        };
+
+        Explanation {
+            verdict,
+            decided_by,
+            matchers: matchers
+                .into_iter()
+                .map(|matcher| MatcherExplanation {
+                    matcher: matcher.clone(),
+                    failed: matcher.failed_criterion(event),
+                })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,727 @@
+// src/filter_expr.rs
+use std::{collections::HashSet, fmt, str::FromStr};
+use tracing::Level;
+
+use crate::{
+    LevelMatch, MatchOrder, Matcher, MatcherSet, TraceLevel,
+    pattern::{normalize_field_pattern, normalize_span_field_pattern, split_field_pattern},
+};
+
+// Text filters such as `level>=info && module=my_app::net::* && !target=hyper*`:
+//
+//   expression := [ "first:" ] clause ( "||" clause )*
+//   clause     := term ( "&&" term )*
+//   term       := "!" "(" criterion ( "&&" criterion )* ")" | "!" criterion | criterion
+//   criterion  := "all"
+//               | "level" ( ">=" | "<=" | "=" ) level [ ".." level ]
+//               | key "=" value ( "|" value )*
+//               | "field." name [ operator value ]
+//
+// A clause becomes an include matcher of its plain criteria, and every negated term an
+// exclude matcher of those same criteria plus its own. A clause with only negated terms
+// just excludes. Excludes apply to the whole set, so a clause mixing plain and negated
+// terms is rejected when another clause includes events too. A leading `first:` makes a
+// first-match set instead, where each matcher only shadows the clauses after it, so a
+// mixed clause is only rejected when an including clause follows it. Values are bare
+// words or double-quoted strings, except that a bare `~` regex runs to the end of its
+// term and may hold `|` and balanced parentheses. `span_field` values are spelled
+// `span{field=value,...}`.
+
+// Marks a first-match set, since the clauses alone read back as excludes-first
+const FIRST_MATCH_PREFIX: &str = "first:";
+
+/// A filter expression that could not be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterParseError {
+    /// Byte offset of the problem in the expression
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for FilterParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for FilterParseError {}
+
+impl MatcherSet {
+    /// Parse a filter expression into the matchers it describes
+    pub fn parse(expression: &str) -> Result<Self, FilterParseError> {
+        Parser::new(expression).parse_expression()
+    }
+}
+
+impl Matcher {
+    /// Parse a filter expression describing exactly one matcher
+    pub fn parse(expression: &str) -> Result<Self, FilterParseError> {
+        let set = MatcherSet::parse(expression)?;
+        let mut matchers = set.iter_matchers();
+        match (matchers.pop(), matchers.is_empty()) {
+            (Some(matcher), true) => Ok(matcher.clone()),
+            _ => Err(FilterParseError {
+                position: 0,
+                message: format!(
+                    "expected a single matcher, found {}",
+                    set.iter_matchers().len()
+                ),
+            }),
+        }
+    }
+}
+
+impl FromStr for MatcherSet {
+    type Err = FilterParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl FromStr for Matcher {
+    type Err = FilterParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for Matcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut terms = Vec::new();
+
+        let level = level_name(self.level);
+        match self.level_match {
+            LevelMatch::AtLeast if self.level.0 == Level::TRACE => {}
+            LevelMatch::AtLeast => terms.push(format!("level>={level}")),
+            LevelMatch::Exactly => terms.push(format!("level={level}")),
+            LevelMatch::AtMost => terms.push(format!("level<={level}")),
+            LevelMatch::Between(other) => {
+                terms.push(format!("level={level}..{}", level_name(other)))
+            }
+        }
+
+        for key in PatternKey::ALL {
+            let patterns = key.patterns(self);
+            if !patterns.is_empty() {
+                let values: Vec<_> = patterns.iter().map(|value| quote(value)).collect();
+                terms.push(format!("{}={}", key.name(), values.join("|")));
+            }
+        }
+
+        for pattern in &self.field_patterns {
+            terms.push(match split_field_pattern(pattern) {
+                Some((field, None)) => format!("field.{field}"),
+                Some((field, Some((operator, value)))) => {
+                    // A value starting like an operator would merge into the one before it
+                    let value = if value.starts_with(['=', '!', '<', '>', '~']) {
+                        quoted(value)
+                    } else {
+                        quote(value)
+                    };
+                    format!("field.{field}{operator}{value}")
+                }
+                None => format!("field.{pattern}"),
+            });
+        }
+
+        let clause = if terms.is_empty() {
+            "all".to_string()
+        } else {
+            terms.join(" && ")
+        };
+        if self.include {
+            f.write_str(&clause)
+        } else {
+            write!(f, "!({clause})")
+        }
+    }
+}
+
+impl fmt::Display for MatcherSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Order only matters for first-match sets, sort the rest for a stable rendering
+        // with includes first
+        let mut clauses: Vec<_> = self
+            .iter_matchers()
+            .into_iter()
+            .map(|matcher| (!matcher.include, matcher.to_string()))
+            .collect();
+        if self.order() == MatchOrder::ExcludesFirst {
+            clauses.sort();
+        }
+
+        let clauses: Vec<_> = clauses.into_iter().map(|(_, clause)| clause).collect();
+        match self.order() {
+            MatchOrder::ExcludesFirst => f.write_str(&clauses.join(" || ")),
+            MatchOrder::FirstMatch if clauses.is_empty() => f.write_str(FIRST_MATCH_PREFIX),
+            MatchOrder::FirstMatch => {
+                write!(f, "{FIRST_MATCH_PREFIX} {}", clauses.join(" || "))
+            }
+        }
+    }
+}
+
+fn level_name(level: TraceLevel) -> String {
+    level.to_string().to_lowercase()
+}
+
+// Characters that end a bare value
+const VALUE_DELIMITERS: &str = "\"|&()";
+
+fn quote(value: &str) -> String {
+    let bare = !value.is_empty()
+        && !value.contains(|c: char| c.is_whitespace() || VALUE_DELIMITERS.contains(c));
+    if bare {
+        value.to_string()
+    } else {
+        quoted(value)
+    }
+}
+
+fn quoted(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// Keys holding a list of patterns, any of which may match
+#[derive(Debug, Clone, Copy)]
+enum PatternKey {
+    Module,
+    File,
+    Span,
+    Target,
+    Thread,
+    Ancestor,
+    SpanPath,
+    SpanField,
+    Message,
+}
+
+impl PatternKey {
+    const ALL: [PatternKey; 9] = [
+        PatternKey::Module,
+        PatternKey::File,
+        PatternKey::Span,
+        PatternKey::Target,
+        PatternKey::Thread,
+        PatternKey::Ancestor,
+        PatternKey::SpanPath,
+        PatternKey::SpanField,
+        PatternKey::Message,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            PatternKey::Module => "module",
+            PatternKey::File => "file",
+            PatternKey::Span => "span",
+            PatternKey::Target => "target",
+            PatternKey::Thread => "thread",
+            PatternKey::Ancestor => "ancestor",
+            PatternKey::SpanPath => "span_path",
+            PatternKey::SpanField => "span_field",
+            PatternKey::Message => "message",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|key| key.name() == name)
+    }
+
+    fn patterns(self, matcher: &Matcher) -> &Vec<String> {
+        match self {
+            PatternKey::Module => &matcher.module_patterns,
+            PatternKey::File => &matcher.file_patterns,
+            PatternKey::Span => &matcher.span_patterns,
+            PatternKey::Target => &matcher.target_patterns,
+            PatternKey::Thread => &matcher.thread_patterns,
+            PatternKey::Ancestor => &matcher.ancestor_patterns,
+            PatternKey::SpanPath => &matcher.span_path_patterns,
+            PatternKey::SpanField => &matcher.span_field_patterns,
+            PatternKey::Message => &matcher.message_patterns,
+        }
+    }
+
+    fn patterns_mut(self, matcher: &mut Matcher) -> &mut Vec<String> {
+        match self {
+            PatternKey::Module => &mut matcher.module_patterns,
+            PatternKey::File => &mut matcher.file_patterns,
+            PatternKey::Span => &mut matcher.span_patterns,
+            PatternKey::Target => &mut matcher.target_patterns,
+            PatternKey::Thread => &mut matcher.thread_patterns,
+            PatternKey::Ancestor => &mut matcher.ancestor_patterns,
+            PatternKey::SpanPath => &mut matcher.span_path_patterns,
+            PatternKey::SpanField => &mut matcher.span_field_patterns,
+            PatternKey::Message => &mut matcher.message_patterns,
+        }
+    }
+}
+
+struct Criterion {
+    kind: CriterionKind,
+    position: usize,
+}
+
+enum CriterionKind {
+    All,
+    Level(TraceLevel, LevelMatch),
+    Patterns(PatternKey, Vec<String>),
+    Field(String),
+}
+
+enum Term {
+    Plain(Criterion),
+    /// Position of the `!` and the criteria it negates
+    Negated(usize, Vec<Criterion>),
+}
+
+// The matchers of one clause
+struct Clause {
+    matchers: Vec<Matcher>,
+    includes: bool,
+    /// Position of the first negated term in a clause that also includes
+    scoped_negation: Option<usize>,
+}
+
+// A matcher being assembled from one clause, remembering which criteria it already has
+#[derive(Clone)]
+struct Draft {
+    matcher: Matcher,
+    constrained: HashSet<&'static str>,
+}
+
+impl Draft {
+    fn new() -> Self {
+        Self {
+            matcher: Matcher::trace(),
+            constrained: HashSet::new(),
+        }
+    }
+
+    fn apply(&mut self, criterion: Criterion) -> Result<(), FilterParseError> {
+        let key = match &criterion.kind {
+            CriterionKind::All | CriterionKind::Field(_) => None,
+            CriterionKind::Level(..) => Some("level"),
+            CriterionKind::Patterns(key, _) => Some(key.name()),
+        };
+        if let Some(key) = key
+            && !self.constrained.insert(key)
+        {
+            return Err(FilterParseError {
+                position: criterion.position,
+                message: format!(
+                    "`{key}` is already constrained in this clause, list alternatives with `|`"
+                ),
+            });
+        }
+
+        match criterion.kind {
+            CriterionKind::All => {}
+            CriterionKind::Level(level, level_match) => {
+                self.matcher.level = level;
+                self.matcher.level_match = level_match;
+            }
+            CriterionKind::Patterns(PatternKey::SpanField, patterns) => {
+                self.matcher.span_field_patterns.extend(
+                    patterns
+                        .iter()
+                        .map(|pattern| normalize_span_field_pattern(pattern)),
+                );
+            }
+            CriterionKind::Patterns(key, patterns) => {
+                key.patterns_mut(&mut self.matcher).extend(patterns);
+            }
+            CriterionKind::Field(pattern) => self.matcher.field_patterns.push(pattern),
+        }
+        Ok(())
+    }
+
+    fn finish(mut self, include: bool) -> Matcher {
+        self.matcher.include = include;
+        self.matcher.recompile_patterns();
+        self.matcher
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self { input, position: 0 }
+    }
+
+    fn parse_expression(&mut self) -> Result<MatcherSet, FilterParseError> {
+        let mut set = MatcherSet::empty();
+        self.skip_whitespace();
+        if self.eat(FIRST_MATCH_PREFIX) {
+            set.set_order(MatchOrder::FirstMatch);
+            self.skip_whitespace();
+        }
+        if self.rest().is_empty() {
+            return Ok(set);
+        }
+
+        let mut clauses = Vec::new();
+        loop {
+            clauses.push(self.parse_clause()?);
+            self.skip_whitespace();
+            if self.rest().is_empty() {
+                break;
+            }
+            if !self.eat("||") {
+                return Err(self.error("expected `&&`, `||` or the end of the expression"));
+            }
+        }
+
+        // An exclude would also silence what the other clauses include
+        match set.order() {
+            MatchOrder::ExcludesFirst => {
+                let including = clauses.iter().filter(|clause| clause.includes).count();
+                if including > 1
+                    && let Some(position) = clauses.iter().find_map(|clause| clause.scoped_negation)
+                {
+                    return Err(FilterParseError {
+                        position,
+                        message: "negated terms next to plain ones can't be combined with \
+                                  other including `||` clauses, since excludes apply to the \
+                                  whole set"
+                            .to_string(),
+                    });
+                }
+            }
+            MatchOrder::FirstMatch => {
+                let shadowing = clauses.iter().enumerate().find_map(|(index, clause)| {
+                    let position = clause.scoped_negation?;
+                    clauses[index + 1..]
+                        .iter()
+                        .any(|later| later.includes)
+                        .then_some(position)
+                });
+                if let Some(position) = shadowing {
+                    return Err(FilterParseError {
+                        position,
+                        message: "negated terms next to plain ones can't be followed by \
+                                  including `||` clauses, since excludes apply to every \
+                                  later clause"
+                            .to_string(),
+                    });
+                }
+            }
+        }
+
+        for matcher in clauses.into_iter().flat_map(|clause| clause.matchers) {
+            set.add_matcher(matcher);
+        }
+        Ok(set)
+    }
+
+    fn parse_clause(&mut self) -> Result<Clause, FilterParseError> {
+        let mut terms = vec![self.parse_term()?];
+        loop {
+            self.skip_whitespace();
+            if !self.eat("&&") {
+                break;
+            }
+            terms.push(self.parse_term()?);
+        }
+
+        let mut positive = Draft::new();
+        let mut has_positive = false;
+        let mut negated = Vec::new();
+        let mut first_negation = None;
+        for term in terms {
+            match term {
+                Term::Plain(criterion) => {
+                    positive.apply(criterion)?;
+                    has_positive = true;
+                }
+                Term::Negated(position, criteria) => {
+                    first_negation.get_or_insert(position);
+                    negated.push(criteria);
+                }
+            }
+        }
+
+        let mut matchers = Vec::new();
+        for criteria in negated {
+            let mut exclude = positive.clone();
+            for criterion in criteria {
+                exclude.apply(criterion)?;
+            }
+            matchers.push(exclude.finish(false));
+        }
+        if has_positive {
+            matchers.push(positive.finish(true));
+        }
+        Ok(Clause {
+            matchers,
+            includes: has_positive,
+            scoped_negation: first_negation.filter(|_| has_positive),
+        })
+    }
+
+    fn parse_term(&mut self) -> Result<Term, FilterParseError> {
+        self.skip_whitespace();
+        let position = self.position;
+        if !self.eat("!") {
+            return Ok(Term::Plain(self.parse_criterion()?));
+        }
+
+        self.skip_whitespace();
+        if !self.eat("(") {
+            return Ok(Term::Negated(position, vec![self.parse_criterion()?]));
+        }
+
+        let mut criteria = vec![self.parse_criterion()?];
+        loop {
+            self.skip_whitespace();
+            if self.eat(")") {
+                return Ok(Term::Negated(position, criteria));
+            }
+            if !self.eat("&&") {
+                return Err(self.error("expected `&&` or `)`"));
+            }
+            criteria.push(self.parse_criterion()?);
+        }
+    }
+
+    fn parse_criterion(&mut self) -> Result<Criterion, FilterParseError> {
+        self.skip_whitespace();
+        let position = self.position;
+        match self.peek() {
+            Some('!') => return Err(self.error("negations cannot be nested")),
+            Some('(') => return Err(self.error("parentheses are only supported after `!`")),
+            _ => {}
+        }
+
+        let key = self.take_while(|c| c.is_alphanumeric() || c == '_');
+        let kind = match key {
+            "" => {
+                return Err(
+                    self.error("expected a criterion such as `level>=info` or `module=app::*`")
+                );
+            }
+            "all" => CriterionKind::All,
+            "level" => self.parse_level()?,
+            "field" => self.parse_field()?,
+            key => {
+                let Some(key) = PatternKey::from_name(key) else {
+                    return Err(FilterParseError {
+                        position,
+                        message: format!("unknown key `{key}`"),
+                    });
+                };
+                self.skip_whitespace();
+                if !self.eat("=") {
+                    return Err(self.error(&format!("expected `=` after `{}`", key.name())));
+                }
+                CriterionKind::Patterns(key, self.parse_values()?)
+            }
+        };
+
+        Ok(Criterion { kind, position })
+    }
+
+    fn parse_level(&mut self) -> Result<CriterionKind, FilterParseError> {
+        self.skip_whitespace();
+        let level_match = if self.eat(">=") {
+            LevelMatch::AtLeast
+        } else if self.eat("<=") {
+            LevelMatch::AtMost
+        } else if self.eat("=") {
+            LevelMatch::Exactly
+        } else {
+            return Err(self.error("expected `>=`, `<=` or `=` after `level`"));
+        };
+
+        let level = self.parse_level_name()?;
+        if !self.eat("..") {
+            return Ok(CriterionKind::Level(level, level_match));
+        }
+        if level_match != LevelMatch::Exactly {
+            return Err(self.error("level ranges are written `level=from..to`"));
+        }
+        let other = self.parse_level_name()?;
+        Ok(CriterionKind::Level(level, LevelMatch::Between(other)))
+    }
+
+    fn parse_level_name(&mut self) -> Result<TraceLevel, FilterParseError> {
+        self.skip_whitespace();
+        let position = self.position;
+        let name = self.take_while(|c| c.is_alphabetic());
+        let level = match name.to_lowercase().as_str() {
+            "error" => Level::ERROR,
+            "warn" => Level::WARN,
+            "info" => Level::INFO,
+            "debug" => Level::DEBUG,
+            "trace" => Level::TRACE,
+            _ => {
+                return Err(FilterParseError {
+                    position,
+                    message: format!("unknown level `{name}`"),
+                });
+            }
+        };
+        Ok(TraceLevel(level))
+    }
+
+    fn parse_field(&mut self) -> Result<CriterionKind, FilterParseError> {
+        if !self.eat(".") {
+            return Err(self.error("expected `.` and a field name after `field`"));
+        }
+        let field = self.take_while(|c| {
+            !c.is_whitespace() && !VALUE_DELIMITERS.contains(c) && !"=!<>~".contains(c)
+        });
+        if field.is_empty() {
+            return Err(self.error("expected a field name"));
+        }
+
+        self.skip_whitespace();
+        let Some(operator) = [">=", "<=", "!=", "=", "~", ">", "<"]
+            .into_iter()
+            .find(|operator| self.eat(operator))
+        else {
+            return Ok(CriterionKind::Field(field.to_string()));
+        };
+
+        let value = if operator == "~" {
+            self.parse_regex()?
+        } else {
+            self.parse_value()?
+        };
+        if self.peek() == Some('|') && !self.rest().starts_with("||") {
+            return Err(self.error("field criteria take a single value"));
+        }
+        Ok(CriterionKind::Field(normalize_field_pattern(&format!(
+            "{field} {operator} {value}"
+        ))))
+    }
+
+    fn parse_values(&mut self) -> Result<Vec<String>, FilterParseError> {
+        let mut values = vec![self.parse_value()?];
+        // A single `|` right after a value separates alternatives, `||` separates clauses
+        while self.peek() == Some('|') && !self.rest().starts_with("||") {
+            self.position += 1;
+            values.push(self.parse_value()?);
+        }
+        Ok(values)
+    }
+
+    // A bare regex runs to the end of its term, so it may hold `|` and balanced groups
+    fn parse_regex(&mut self) -> Result<String, FilterParseError> {
+        self.skip_whitespace();
+        if self.peek() == Some('"') {
+            return self.parse_value();
+        }
+
+        let start = self.position;
+        let rest = self.rest();
+        let mut depth = 0usize;
+        let mut end = rest.len();
+        let mut chars = rest.char_indices();
+        while let Some((offset, c)) = chars.next() {
+            let tail = &rest[offset..];
+            match c {
+                '\\' => {
+                    chars.next();
+                }
+                '(' => depth += 1,
+                ')' if depth == 0 => {
+                    end = offset;
+                    break;
+                }
+                ')' => depth -= 1,
+                _ if depth == 0
+                    && (c.is_whitespace() || tail.starts_with("&&") || tail.starts_with("||")) =>
+                {
+                    end = offset;
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        if depth > 0 {
+            return Err(FilterParseError {
+                position: start,
+                message: "unbalanced `(` in regex, quote it as `~ \"...\"`".to_string(),
+            });
+        }
+        if end == 0 {
+            return Err(self.error("expected a value"));
+        }
+        self.position += end;
+        Ok(rest[..end].to_string())
+    }
+
+    fn parse_value(&mut self) -> Result<String, FilterParseError> {
+        self.skip_whitespace();
+        let start = self.position;
+        if !self.eat("\"") {
+            let value = self.take_while(|c| !c.is_whitespace() && !VALUE_DELIMITERS.contains(c));
+            if value.is_empty() {
+                return Err(self.error("expected a value"));
+            }
+            return Ok(value.to_string());
+        }
+
+        let mut value = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((offset, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.position += offset + 1;
+                    return Ok(value);
+                }
+                '\\' => match chars.next() {
+                    Some((_, escaped)) => value.push(escaped),
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+        Err(FilterParseError {
+            position: start,
+            message: "unterminated string".to_string(),
+        })
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        if self.rest().starts_with(token) {
+            self.position += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn take_while(&mut self, accept: impl Fn(char) -> bool) -> &'a str {
+        let rest = self.rest();
+        let end = rest.find(|c: char| !accept(c)).unwrap_or(rest.len());
+        self.position += end;
+        &rest[..end]
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.take_while(char::is_whitespace);
+    }
+
+    fn error(&self, message: &str) -> FilterParseError {
+        FilterParseError {
+            position: self.position,
+            message: message.to_string(),
+        }
+    }
+}
@@ -4,16 +4,54 @@ pub use tracer::Tracer;
 use tracer::{DroppedEventCallback, EventCallback, SilencedEventCallback};
 
 mod trace_event;
-pub use trace_event::{TraceData, TraceEvent, TraceEventId, TracingLevel};
+pub use trace_event::{
+    PANIC_TARGET, SpanEventKind, SpanLifecycle, SpanSnapshot, TraceData, TraceEvent, TraceEventId,
+    TracingLevel,
+};
+
+mod trace_value;
+pub use trace_value::TraceValue;
+
+mod pattern;
 
 mod trace_matcher;
-pub use trace_matcher::{Matcher, MatcherSet, TraceLevel, matches};
+pub use trace_matcher::{
+    LevelMatch, MatchOrder, Matcher, MatcherSet, PatternError, TraceLevel, matches,
+};
+
+mod filter_expr;
+pub use filter_expr::FilterParseError;
+
+mod env_filter;
+
+mod explain;
+pub use explain::{Criterion, Explanation, MatcherExplanation, Verdict};
+
+mod event_queue;
+pub use event_queue::{BLOCK_TIMEOUT, EventSender, OverflowPolicy};
+use event_queue::{DispatchingGuard, EventReceiver, event_queue, is_dispatching, make_room};
+
+mod callsite_filter;
+use callsite_filter::CallsiteFilter;
 
 mod tracing_dispatcher;
-use tracing_dispatcher::{DispatcherCommand, ResultSender, TraceCounters, TracingDispatcher};
+use tracing_dispatcher::{
+    DispatcherCommand, DispatcherState, ResultSender, TraceCounters, TracingDispatcher,
+    invalid_tab_message, lock_state,
+};
 
 mod tracer_config;
-pub use tracer_config::{TracerConfig, TracerTab};
+pub use tracer_config::{SpanEvents, TracerConfig, TracerTab};
 
 mod tracing_subscriber;
 pub use tracing_subscriber::TracingSubscriber;
+
+#[cfg(feature = "layer")]
+mod tracer_layer;
+#[cfg(feature = "layer")]
+pub use tracer_layer::TracerLayer;
+
+#[cfg(feature = "log")]
+mod log_bridge;
+#[cfg(feature = "log")]
+pub use log_bridge::TracerLogger;
@@ -0,0 +1,50 @@
+// src/log_bridge.rs
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
+use crate::{CallsiteFilter, EventSender, TraceData, trace_event::log_level_to_tracing};
+
+// `log::Log` implementation feeding `log` crate records into a tracer's dispatcher
+pub struct TracerLogger {
+    sender: EventSender,
+    id_counter: Arc<AtomicU64>,
+    callsite_filter: Arc<CallsiteFilter>,
+}
+
+impl TracerLogger {
+    pub fn new(sender: impl Into<EventSender>, id_counter: Arc<AtomicU64>) -> Self {
+        Self {
+            sender: sender.into(),
+            id_counter,
+            callsite_filter: Arc::new(CallsiteFilter::accept_all()),
+        }
+    }
+
+    // Skip records more verbose than any tab of the owning tracer could capture
+    pub(crate) fn with_callsite_filter(mut self, callsite_filter: Arc<CallsiteFilter>) -> Self {
+        self.callsite_filter = callsite_filter;
+        self
+    }
+}
+
+impl log::Log for TracerLogger {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        self.callsite_filter.max_event_level() >= log_level_to_tracing(metadata.level())
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        // The `log` macros don't consult `enabled` themselves
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let event_id = self.id_counter.fetch_add(1, Ordering::SeqCst);
+        let _ = self
+            .sender
+            .send(Arc::new(TraceData::from_log_record(event_id, record)));
+    }
+
+    fn flush(&self) {}
+}
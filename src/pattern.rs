@@ -0,0 +1,559 @@
+// src/pattern.rs
+use regex::Regex;
+use std::{collections::HashMap, iter::Peekable, str::CharIndices};
+
+use crate::{SpanSnapshot, TraceValue};
+
+// Prefix for a raw regex in place of a glob
+pub(crate) const PATTERN_REGEX_PREFIX: &str = "re:";
+
+// A glob compiled once, so matching an event never parses the pattern again.
+//
+// `*` matches any run of characters within one path segment and `**` any run at all;
+// segments are separated by `::` or `/`. `**/` and `**::` also match no segments, so
+// `src/**/*.rs` matches `src/lib.rs`. `?` matches one character and `[abc]`, `[a-z]`
+// or `[!abc]` one character from a class, neither of them `/` or `:`. `\` escapes the
+// next character, and everything else, regex metacharacters included, matches itself.
+// A lone `*` still matches everything, and `re:` introduces a regex searched for in
+// the value.
+#[derive(Debug, Clone)]
+pub(crate) enum Pattern {
+    /// `*` or `**`
+    Any,
+    /// No wildcards at all
+    Exact(String),
+    /// A literal followed by a trailing `**`
+    Prefix(String),
+    /// Any other glob, translated to an anchored regex
+    Glob(Regex),
+    /// `re:<regex>`
+    Regex(Regex),
+    /// Did not compile, so never matches
+    Invalid(String),
+}
+
+impl Pattern {
+    /// A glob over paths such as modules, targets and files
+    pub(crate) fn new(pattern: &str) -> Self {
+        Self::compile(pattern, true)
+    }
+
+    /// A glob over free text such as messages and field values, where `*`, `?` and
+    /// classes match separators too
+    pub(crate) fn text(pattern: &str) -> Self {
+        Self::compile(pattern, false)
+    }
+
+    fn compile(pattern: &str, separators: bool) -> Self {
+        if pattern == "*" || pattern == "**" {
+            return Pattern::Any;
+        }
+        if let Some(regex) = pattern.strip_prefix(PATTERN_REGEX_PREFIX) {
+            return Regex::new(regex).map_or_else(invalid_regex(Pattern::Invalid), Pattern::Regex);
+        }
+
+        let tokens = match parse_glob(pattern, separators) {
+            Ok(tokens) => tokens,
+            Err(reason) => return Pattern::Invalid(reason),
+        };
+        let literal = |tokens: &[Token]| {
+            tokens
+                .iter()
+                .map(|token| match token {
+                    Token::Char(c) => Some(*c),
+                    _ => None,
+                })
+                .collect::<Option<String>>()
+        };
+        if let Some(exact) = literal(&tokens) {
+            return Pattern::Exact(exact);
+        }
+        if let Some((Token::AnyRun, prefix)) = tokens.split_last()
+            && let Some(prefix) = literal(prefix)
+        {
+            return Pattern::Prefix(prefix);
+        }
+
+        // `(?s)` lets wildcards run across the lines of a multi-line message
+        let regex: String = tokens
+            .iter()
+            .map(|token| token.to_regex(separators))
+            .collect();
+        Regex::new(&format!("(?s)^{regex}$"))
+            .map_or_else(invalid_regex(Pattern::Invalid), Pattern::Glob)
+    }
+
+    pub(crate) fn matches(&self, value: &str) -> bool {
+        match self {
+            Pattern::Any => true,
+            Pattern::Exact(exact) => value == exact,
+            Pattern::Prefix(prefix) => value.starts_with(prefix.as_str()),
+            Pattern::Glob(regex) | Pattern::Regex(regex) => regex.is_match(value),
+            Pattern::Invalid(_) => false,
+        }
+    }
+
+    /// Why the pattern did not compile
+    pub(crate) fn error(&self) -> Option<&str> {
+        match self {
+            Pattern::Invalid(reason) => Some(reason),
+            _ => None,
+        }
+    }
+}
+
+// Keep the reason from a regex error, which otherwise repeats the pattern over
+// several lines
+fn invalid_regex<T>(invalid: fn(String) -> T) -> impl FnOnce(regex::Error) -> T {
+    move |error| {
+        let message = error.to_string();
+        let reason = message
+            .lines()
+            .rev()
+            .find_map(|line| line.strip_prefix("error: "))
+            .unwrap_or(&message);
+        invalid(format!("invalid regex, {reason}"))
+    }
+}
+
+#[derive(Debug)]
+enum Token {
+    Char(char),
+    /// `?`
+    AnyChar,
+    /// `[...]`, one character within or, when negated, outside the ranges
+    Class {
+        ranges: Vec<(char, char)>,
+        negated: bool,
+    },
+    /// `*`
+    SegmentRun,
+    /// `**`
+    AnyRun,
+    /// `**/` or `**::`, nothing or any run ending in the separator
+    AnySegments(&'static str),
+}
+
+impl Token {
+    fn to_regex(&self, separators: bool) -> String {
+        // Characters a wildcard within a segment may not match
+        let excluded = if separators { "/:" } else { "" };
+        match self {
+            Token::Char(c) => regex::escape(c.encode_utf8(&mut [0; 4])),
+            Token::AnyChar if separators => format!("[^{excluded}]"),
+            Token::AnyChar => ".".to_string(),
+            Token::Class { ranges, negated } => {
+                let ranges: String = ranges
+                    .iter()
+                    .map(|(lo, hi)| format!("\\x{{{:X}}}-\\x{{{:X}}}", *lo as u32, *hi as u32))
+                    .collect();
+                match (negated, separators) {
+                    (true, _) => format!("[^{ranges}{excluded}]"),
+                    (false, true) => format!("[{ranges}&&[^{excluded}]]"),
+                    (false, false) => format!("[{ranges}]"),
+                }
+            }
+            Token::SegmentRun => format!("[^{excluded}]*"),
+            Token::AnyRun => ".*".to_string(),
+            Token::AnySegments(separator) => format!("(?:.*{})?", regex::escape(separator)),
+        }
+    }
+}
+
+// Parse a glob into tokens, failing on a dangling `\` or an unclosed class
+fn parse_glob(pattern: &str, separators: bool) -> Result<Vec<Token>, String> {
+    let dangling_escape = || "`\\` at the end escapes nothing".to_string();
+    let mut tokens = Vec::new();
+    let mut chars = pattern.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        let token = match c {
+            '\\' => Token::Char(chars.next().ok_or_else(dangling_escape)?.1),
+            '?' => Token::AnyChar,
+            '[' => parse_class(&mut chars).ok_or_else(|| format!("unclosed `[` at {index}"))?,
+            '*' if separators && chars.next_if(|(_, c)| *c == '*').is_none() => Token::SegmentRun,
+            '*' => {
+                while chars.next_if(|(_, c)| *c == '*').is_some() {}
+
+                // Only a whole segment of `**` may stand for no segments at all
+                let rest = &pattern[chars.peek().map_or(pattern.len(), |(i, _)| *i)..];
+                let at_segment_start = index == 0 || ends_with_separator(&tokens);
+                let separator = ["/", "::"]
+                    .into_iter()
+                    .find(|separator| rest.starts_with(separator));
+                match separator {
+                    Some(separator) if separators && at_segment_start => {
+                        for _ in 0..separator.len() {
+                            chars.next();
+                        }
+                        Token::AnySegments(separator)
+                    }
+                    _ => Token::AnyRun,
+                }
+            }
+            c => Token::Char(c),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn ends_with_separator(tokens: &[Token]) -> bool {
+    matches!(
+        tokens,
+        [.., Token::Char('/')]
+            | [.., Token::Char(':'), Token::Char(':')]
+            | [.., Token::AnySegments(_)]
+    )
+}
+
+// Parse the rest of a class after its `[`. A `]` right after the `[` or `[!` is part
+// of the class, as is a `-` at either end.
+fn parse_class(chars: &mut Peekable<CharIndices<'_>>) -> Option<Token> {
+    let negated = chars.next_if(|(_, c)| *c == '!' || *c == '^').is_some();
+    let mut ranges = Vec::new();
+    let class_char = |chars: &mut Peekable<CharIndices<'_>>| match chars.next()?.1 {
+        '\\' => chars.next().map(|(_, c)| c),
+        c => Some(c),
+    };
+
+    loop {
+        let start = match chars.peek()? {
+            (_, ']') if !ranges.is_empty() => {
+                chars.next();
+                break;
+            }
+            _ => class_char(chars)?,
+        };
+
+        let mut lookahead = chars.clone();
+        let is_range = lookahead.next().is_some_and(|(_, c)| c == '-')
+            && lookahead.next().is_some_and(|(_, c)| c != ']');
+        let end = if is_range {
+            chars.next();
+            class_char(chars)?
+        } else {
+            start
+        };
+        ranges.push((start, end));
+    }
+    Some(Token::Class { ranges, negated })
+}
+
+// Compile every pattern in a list
+pub(crate) fn compile_all(patterns: &[String]) -> Vec<Pattern> {
+    patterns
+        .iter()
+        .map(|pattern| Pattern::new(pattern))
+        .collect()
+}
+
+// Whether any of the compiled patterns matches the value
+pub(crate) fn any_matches(patterns: &[Pattern], value: &str) -> bool {
+    patterns.iter().any(|pattern| pattern.matches(value))
+}
+
+// A compiled message pattern: `glob:<glob>` matches the whole message,
+// `regex:<regex>` searches it, and anything else is a plain substring
+#[derive(Debug, Clone)]
+pub(crate) enum MessagePattern {
+    Contains(String),
+    Glob(Pattern),
+    Regex(Regex),
+    /// Did not compile, so never matches
+    Invalid(String),
+}
+
+pub(crate) const MESSAGE_GLOB_PREFIX: &str = "glob:";
+pub(crate) const MESSAGE_REGEX_PREFIX: &str = "regex:";
+
+impl MessagePattern {
+    pub(crate) fn new(pattern: &str) -> Self {
+        if let Some(glob) = pattern.strip_prefix(MESSAGE_GLOB_PREFIX) {
+            MessagePattern::Glob(Pattern::text(glob))
+        } else if let Some(regex) = pattern.strip_prefix(MESSAGE_REGEX_PREFIX) {
+            Regex::new(regex).map_or_else(
+                invalid_regex(MessagePattern::Invalid),
+                MessagePattern::Regex,
+            )
+        } else {
+            MessagePattern::Contains(pattern.to_string())
+        }
+    }
+
+    pub(crate) fn matches(&self, message: &str) -> bool {
+        match self {
+            MessagePattern::Contains(text) => message.contains(text.as_str()),
+            MessagePattern::Glob(pattern) => pattern.matches(message),
+            MessagePattern::Regex(regex) => regex.is_match(message),
+            MessagePattern::Invalid(_) => false,
+        }
+    }
+
+    /// Why the pattern did not compile
+    pub(crate) fn error(&self) -> Option<&str> {
+        match self {
+            MessagePattern::Glob(pattern) => pattern.error(),
+            MessagePattern::Invalid(reason) => Some(reason),
+            _ => None,
+        }
+    }
+}
+
+// A compiled field pattern: `name` for existence, `name = glob`, `name != glob`,
+// `name ~ regex`, or `name > 5` with any of `>`, `>=`, `<`, `<=`
+#[derive(Debug, Clone)]
+pub(crate) struct FieldPredicate {
+    field: String,
+    test: FieldTest,
+}
+
+#[derive(Debug, Clone)]
+enum FieldTest {
+    Exists,
+    Equals(Pattern),
+    NotEquals(Pattern),
+    Regex(Regex),
+    Gt(f64),
+    Ge(f64),
+    Lt(f64),
+    Le(f64),
+    /// Did not parse, so never matches
+    Invalid(String),
+}
+
+// Longest operators first, so `>=` is not read as `>`
+const FIELD_OPERATORS: &[&str] = &[">=", "<=", "!=", ">", "<", "=", "~"];
+
+// Split a field pattern into its field name and, unless it only tests existence, its
+// operator and value. `None` when an operator character does not form an operator.
+pub(crate) fn split_field_pattern(pattern: &str) -> Option<(&str, Option<(&'static str, &str)>)> {
+    let Some(index) = pattern.find(['=', '!', '<', '>', '~']) else {
+        return Some((pattern.trim(), None));
+    };
+
+    let (field, rest) = pattern.split_at(index);
+    let operator = FIELD_OPERATORS.iter().find(|op| rest.starts_with(**op))?;
+    let value = rest[operator.len()..].trim();
+    Some((field.trim(), Some((operator, value))))
+}
+
+// Spell a field pattern as `name op value`, however it was spaced, so matchers built
+// from code and parsed from expressions compare equal. Unsplittable patterns are kept.
+pub(crate) fn normalize_field_pattern(pattern: &str) -> String {
+    match split_field_pattern(pattern) {
+        Some((field, None)) => field.to_string(),
+        Some((field, Some((operator, value)))) if !field.is_empty() => {
+            format!("{field} {operator} {value}").trim_end().to_string()
+        }
+        _ => pattern.to_string(),
+    }
+}
+
+impl FieldPredicate {
+    pub(crate) fn new(pattern: &str) -> Self {
+        let (field, operator, value) = match split_field_pattern(pattern) {
+            Some((field, None)) => return Self::with_test(field, FieldTest::Exists),
+            Some((field, Some((operator, value)))) => (field, operator, value),
+            None => {
+                let reason = "expected one of `>=`, `<=`, `!=`, `>`, `<`, `=` or `~`".to_string();
+                return Self::with_test(pattern.trim(), FieldTest::Invalid(reason));
+            }
+        };
+
+        let number = value.parse::<f64>().ok();
+        let test = match (operator, number) {
+            ("=", _) => FieldTest::Equals(Pattern::text(value)),
+            ("!=", _) => FieldTest::NotEquals(Pattern::text(value)),
+            ("~", _) => {
+                Regex::new(value).map_or_else(invalid_regex(FieldTest::Invalid), FieldTest::Regex)
+            }
+            (">", Some(n)) => FieldTest::Gt(n),
+            (">=", Some(n)) => FieldTest::Ge(n),
+            ("<", Some(n)) => FieldTest::Lt(n),
+            ("<=", Some(n)) => FieldTest::Le(n),
+            _ => FieldTest::Invalid(format!("`{operator}` needs a number, found `{value}`")),
+        };
+        Self::with_test(field, test)
+    }
+
+    fn with_test(field: &str, test: FieldTest) -> Self {
+        let test = if field.is_empty() {
+            FieldTest::Invalid("missing field name".to_string())
+        } else {
+            test
+        };
+        Self {
+            field: field.to_string(),
+            test,
+        }
+    }
+
+    pub(crate) fn matches(&self, fields: &HashMap<String, TraceValue>) -> bool {
+        let Some(value) = self.lookup(fields) else {
+            return false;
+        };
+
+        match &self.test {
+            FieldTest::Exists => true,
+            FieldTest::Equals(pattern) => pattern.matches(&value.to_string()),
+            FieldTest::NotEquals(pattern) => !pattern.matches(&value.to_string()),
+            FieldTest::Regex(regex) => regex.is_match(&value.to_string()),
+            FieldTest::Gt(bound) => number(value).is_some_and(|number| number > *bound),
+            FieldTest::Ge(bound) => number(value).is_some_and(|number| number >= *bound),
+            FieldTest::Lt(bound) => number(value).is_some_and(|number| number < *bound),
+            FieldTest::Le(bound) => number(value).is_some_and(|number| number <= *bound),
+            FieldTest::Invalid(_) => false,
+        }
+    }
+
+    /// Why the pattern did not parse
+    pub(crate) fn error(&self) -> Option<&str> {
+        match &self.test {
+            FieldTest::Equals(pattern) | FieldTest::NotEquals(pattern) => pattern.error(),
+            FieldTest::Invalid(reason) => Some(reason),
+            _ => None,
+        }
+    }
+
+    // Field names may contain dots themselves, so the exact name wins over a nested path
+    fn lookup<'a>(&self, fields: &'a HashMap<String, TraceValue>) -> Option<&'a TraceValue> {
+        if let Some(value) = fields.get(&self.field) {
+            return Some(value);
+        }
+
+        let mut path = self.field.split('.');
+        let mut value = fields.get(path.next()?)?;
+        for key in path {
+            value = value.get(key)?;
+        }
+        Some(value)
+    }
+}
+
+// Numeric value of a field, parsing numbers recorded as text
+fn number(value: &TraceValue) -> Option<f64> {
+    value
+        .as_f64()
+        .or_else(|| value.as_str().and_then(|s| s.trim().parse().ok()))
+}
+
+// A compiled span field pattern, `span{field = value, ...}`: some span the event is
+// inside has a matching name and every field predicate holds on that span's own fields
+#[derive(Debug, Clone)]
+pub(crate) struct SpanFieldPattern {
+    span: Pattern,
+    fields: Vec<FieldPredicate>,
+    /// Did not parse, so never matches
+    invalid: Option<String>,
+}
+
+// Split a span field pattern into its span name and field patterns
+fn split_span_field_pattern(pattern: &str) -> Option<(&str, Vec<&str>)> {
+    let (span, fields) = pattern.trim().strip_suffix('}')?.split_once('{')?;
+    let fields = fields
+        .split(',')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .collect();
+    Some((span.trim(), fields))
+}
+
+// Spell a span field pattern as `span{name op value, ...}`, however it was spaced.
+// Unsplittable patterns are kept.
+pub(crate) fn normalize_span_field_pattern(pattern: &str) -> String {
+    match split_span_field_pattern(pattern) {
+        Some((span, fields)) => {
+            let fields: Vec<_> = fields.into_iter().map(normalize_field_pattern).collect();
+            format!("{span}{{{}}}", fields.join(", "))
+        }
+        None => pattern.to_string(),
+    }
+}
+
+impl SpanFieldPattern {
+    pub(crate) fn new(pattern: &str) -> Self {
+        let Some((span, fields)) = split_span_field_pattern(pattern) else {
+            return Self {
+                span: Pattern::new(pattern),
+                fields: Vec::new(),
+                invalid: Some("expected `span{field = value, ...}`".to_string()),
+            };
+        };
+        let invalid = span.is_empty().then(|| "missing span name".to_string());
+        Self {
+            span: Pattern::new(span),
+            fields: fields.into_iter().map(FieldPredicate::new).collect(),
+            invalid,
+        }
+    }
+
+    /// Why the pattern did not compile
+    pub(crate) fn error(&self) -> Option<&str> {
+        self.invalid
+            .as_deref()
+            .or_else(|| self.span.error())
+            .or_else(|| self.fields.iter().find_map(FieldPredicate::error))
+    }
+
+    pub(crate) fn matches(&self, spans: &[SpanSnapshot]) -> bool {
+        self.invalid.is_none()
+            && spans.iter().any(|span| {
+                self.span.matches(&span.name)
+                    && self.fields.iter().all(|field| field.matches(&span.fields))
+            })
+    }
+}
+
+// A compiled span path pattern, matched against the whole span path from the root
+// span to the innermost one. Segments are separated by `::`; each is a glob matching
+// one span name, and `**` matches any number of spans, including none.
+#[derive(Debug, Clone)]
+pub(crate) struct SpanPathPattern {
+    segments: Vec<PathSegment>,
+}
+
+#[derive(Debug, Clone)]
+enum PathSegment {
+    AnyDepth,
+    Span(Pattern),
+}
+
+impl SpanPathPattern {
+    pub(crate) fn new(pattern: &str) -> Self {
+        let segments = pattern
+            .split("::")
+            .map(|segment| match segment.trim() {
+                "**" => PathSegment::AnyDepth,
+                segment => PathSegment::Span(Pattern::new(segment)),
+            })
+            .collect();
+        Self { segments }
+    }
+
+    /// Why a segment did not compile
+    pub(crate) fn error(&self) -> Option<&str> {
+        self.segments.iter().find_map(|segment| match segment {
+            PathSegment::AnyDepth => None,
+            PathSegment::Span(pattern) => pattern.error(),
+        })
+    }
+
+    pub(crate) fn matches(&self, path: &[&str]) -> bool {
+        // reachable[j]: the segments so far can consume exactly the first j spans
+        let mut reachable = vec![false; path.len() + 1];
+        reachable[0] = true;
+        for segment in &self.segments {
+            let mut next = vec![false; path.len() + 1];
+            for j in 0..=path.len() {
+                next[j] = match segment {
+                    PathSegment::AnyDepth => reachable[j] || (j > 0 && next[j - 1]),
+                    PathSegment::Span(pattern) => {
+                        j > 0 && reachable[j - 1] && pattern.matches(path[j - 1])
+                    }
+                };
+            }
+            reachable = next;
+        }
+        reachable[path.len()]
+    }
+}
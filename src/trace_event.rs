@@ -3,16 +3,20 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt;
 use std::sync::Arc;
-use tracing::{Event, field::Field};
+use std::time::Duration;
+use tracing::{Event, Metadata, field::Field};
 
 use std::fmt::Write as _;
 
 pub use tracing::Level as TracingLevel;
 
-use crate::TraceLevel;
+use crate::{TraceLevel, TraceValue};
 
 pub type TraceEventId = u64;
 
+/// Target of the records produced by `Tracer::install_panic_hook`
+pub const PANIC_TARGET: &str = "panic";
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TraceData {
     pub id: TraceEventId,
@@ -24,13 +28,97 @@ pub struct TraceData {
     pub file: Option<String>,
     pub line: Option<u32>,
     pub message: String,
-    pub fields: HashMap<String, String>,
+    pub fields: HashMap<String, TraceValue>,
     pub span_name: Option<String>,
+    /// Names of `spans` joined as `root::child::leaf`
     pub span_hierarchy: Option<String>,
+    /// Every span the record was emitted in, root first
+    #[serde(default)]
+    pub spans: Vec<SpanSnapshot>,
+    /// Set when this record describes a span lifecycle transition rather than an event
+    #[serde(default)]
+    pub span_event: Option<SpanLifecycle>,
+    /// Name of the thread the record was emitted on, if it has one
+    #[serde(default)]
+    pub thread_name: Option<String>,
+    /// Process-unique id of the thread the record was emitted on
+    #[serde(default)]
+    pub thread_id: u64,
+    /// Tokio task the record was emitted from, when inside a runtime
+    #[serde(default)]
+    pub task_id: Option<u64>,
 }
 
 pub type TraceEvent = Arc<TraceData>;
 
+// A span in a record's ancestry, with the fields recorded on it so far
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpanSnapshot {
+    pub name: String,
+    pub target: String,
+    pub id: u64,
+    pub fields: HashMap<String, TraceValue>,
+}
+
+// Span lifecycle transitions that can be emitted as trace records
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SpanEventKind {
+    New,
+    Enter,
+    Exit,
+    Close,
+}
+
+impl SpanEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SpanEventKind::New => "new",
+            SpanEventKind::Enter => "enter",
+            SpanEventKind::Exit => "exit",
+            SpanEventKind::Close => "close",
+        }
+    }
+}
+
+impl fmt::Display for SpanEventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// Lifecycle record with the span's accumulated busy/idle time (set on exit and close)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpanLifecycle {
+    pub kind: SpanEventKind,
+    pub busy: Option<Duration>,
+    pub idle: Option<Duration>,
+}
+
+impl SpanLifecycle {
+    pub fn new(kind: SpanEventKind) -> Self {
+        Self {
+            kind,
+            busy: None,
+            idle: None,
+        }
+    }
+
+    pub fn with_timings(mut self, busy: Duration, idle: Duration) -> Self {
+        self.busy = Some(busy);
+        self.idle = Some(idle);
+        self
+    }
+}
+
+impl fmt::Display for SpanLifecycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.busy, self.idle) {
+            (Some(busy), Some(idle)) => write!(f, "{} (busy: {busy:?}, idle: {idle:?})", self.kind),
+            _ => write!(f, "{}", self.kind),
+        }
+    }
+}
+
 impl TraceData {
     pub fn new(id: u64, tracing_event: &Event<'_>) -> Self {
         let meta = tracing_event.metadata();
@@ -52,6 +140,160 @@ impl TraceData {
             fields: visitor.fields,
             span_name: None,      // Will be set by subscriber
             span_hierarchy: None, // Will be set by subscriber
+            spans: Vec::new(),
+            span_event: None,
+            thread_name: current_thread_name(),
+            thread_id: current_thread_id(),
+            task_id: current_task_id(),
+        }
+    }
+
+    /// Build a lifecycle record for the span described by `meta`
+    pub fn from_span(id: u64, meta: &Metadata<'_>, lifecycle: SpanLifecycle) -> Self {
+        TraceData {
+            id,
+            timestamp: chrono::Local::now(),
+            level: TraceLevel::from(*meta.level()),
+            target: meta.target().to_string(),
+            name: meta.name().to_string(),
+            module_path: meta.module_path().map(|s| s.to_string()),
+            file: meta.file().map(|s| s.to_string()),
+            line: meta.line(),
+            message: lifecycle.to_string(),
+            fields: HashMap::new(),
+            span_name: Some(meta.name().to_string()),
+            span_hierarchy: None, // Will be set by subscriber
+            spans: Vec::new(),
+            span_event: Some(lifecycle),
+            thread_name: current_thread_name(),
+            thread_id: current_thread_id(),
+            task_id: current_task_id(),
+        }
+    }
+
+    /// Build a record from a `log` crate record, keeping its key-values as fields
+    #[cfg(feature = "log")]
+    pub fn from_log_record(id: u64, record: &log::Record<'_>) -> Self {
+        let mut visitor = LogFieldVisitor::default();
+        let _ = record.key_values().visit(&mut visitor);
+
+        TraceData {
+            id,
+            timestamp: chrono::Local::now(),
+            level: TraceLevel::from(log_level_to_tracing(record.level())),
+            target: record.target().to_string(),
+            name: "log record".to_string(),
+            module_path: record.module_path().map(|s| s.to_string()),
+            file: record.file().map(|s| s.to_string()),
+            line: record.line(),
+            message: record.args().to_string(),
+            fields: visitor.fields,
+            span_name: None,
+            span_hierarchy: None,
+            spans: Vec::new(),
+            span_event: None,
+            thread_name: current_thread_name(),
+            thread_id: current_thread_id(),
+            task_id: current_task_id(),
+        }
+    }
+
+    /// Build an ERROR record with target `panic` from a panic, with the backtrace as a
+    /// field when one was captured. A panic location only knows its file, so the record
+    /// has no module path and module patterns other than `*` never match it.
+    pub fn from_panic(
+        id: u64,
+        info: &std::panic::PanicHookInfo<'_>,
+        backtrace: Option<&std::backtrace::Backtrace>,
+    ) -> Self {
+        let payload = info.payload();
+        let message = if let Some(message) = payload.downcast_ref::<&str>() {
+            message.to_string()
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message.clone()
+        } else {
+            "Box<dyn Any>".to_string()
+        };
+
+        let mut fields = HashMap::new();
+        if let Some(backtrace) = backtrace {
+            fields.insert(
+                "backtrace".to_string(),
+                TraceValue::String(backtrace.to_string()),
+            );
+        }
+
+        TraceData {
+            id,
+            timestamp: chrono::Local::now(),
+            level: TraceLevel(tracing::Level::ERROR),
+            target: PANIC_TARGET.to_string(),
+            name: "panic".to_string(),
+            module_path: None,
+            file: info.location().map(|location| location.file().to_string()),
+            line: info.location().map(|location| location.line()),
+            message,
+            fields,
+            span_name: None,
+            span_hierarchy: None,
+            spans: Vec::new(),
+            span_event: None,
+            thread_name: current_thread_name(),
+            thread_id: current_thread_id(),
+            task_id: current_task_id(),
+        }
+    }
+
+    pub fn is_span_event(&self) -> bool {
+        self.span_event.is_some()
+    }
+
+    /// Innermost span with the given name this record was emitted in
+    pub fn span(&self, name: &str) -> Option<&SpanSnapshot> {
+        self.spans.iter().rev().find(|span| span.name == name)
+    }
+
+    // Attach the span ancestry (root first), deriving the hierarchy string from it
+    pub(crate) fn set_spans(&mut self, spans: Vec<SpanSnapshot>) {
+        self.span_hierarchy = Some(
+            spans
+                .iter()
+                .map(|span| span.name.as_str())
+                .collect::<Vec<_>>()
+                .join("::"),
+        );
+        self.spans = spans;
+    }
+
+    // Enrich an event with the span it was recorded in and that span's ancestors
+    pub(crate) fn inherit_span(&mut self, span_meta: &Metadata<'_>, spans: Vec<SpanSnapshot>) {
+        // Set span-specific information
+        self.span_name = Some(span_meta.name().to_string());
+
+        // If the event doesn't have its own module/file/line info, inherit from span
+        if self.module_path.is_none() {
+            self.module_path = span_meta.module_path().map(|s| s.to_string());
+        }
+        if self.file.is_none() {
+            self.file = span_meta.file().map(|s| s.to_string());
+        }
+        if self.line.is_none() {
+            self.line = span_meta.line();
+        }
+
+        // Merge fields of every span with event fields (inner spans and then the
+        // event itself take precedence)
+        let mut combined_fields = HashMap::new();
+        for span in &spans {
+            combined_fields.extend(span.fields.clone());
+        }
+        combined_fields.extend(std::mem::take(&mut self.fields));
+        self.fields = combined_fields;
+        self.set_spans(spans);
+
+        // Update target if the event target is generic but span has specific target
+        if self.target.is_empty() || self.target == "unknown" {
+            self.target = span_meta.target().to_string();
         }
     }
 
@@ -126,6 +368,26 @@ impl TraceData {
         formatted
     }
 
+    /// Where the record was emitted, like `[thread:worker-1#3 task:12]`
+    pub fn thread_label(&self) -> String {
+        let mut label = format!(
+            "[thread:{}#{}",
+            self.thread_name.as_deref().unwrap_or("unnamed"),
+            self.thread_id
+        );
+        if let Some(task_id) = self.task_id {
+            let _ = write!(label, " task:{task_id}");
+        }
+        label.push(']');
+        label
+    }
+
+    pub fn format_with_thread(&self) -> String {
+        let mut formatted = self.format();
+        formatted.push_str(&format!(" {}", self.thread_label()));
+        formatted
+    }
+
     pub fn format_full(&self) -> String {
         let mut formatted = self.format_with_file();
 
@@ -145,8 +407,47 @@ impl TraceData {
             formatted.push('}');
         }
 
+        for (field, sources) in self.error_causes() {
+            formatted.push_str(&format!("\n  {field} caused by:"));
+            for (index, source) in sources.iter().enumerate() {
+                formatted.push_str(&format!("\n    {index}: {source}"));
+            }
+        }
+
         formatted
     }
+
+    // Source chains of error fields that have one, ordered by field name
+    fn error_causes(&self) -> Vec<(&str, &[String])> {
+        let mut causes: Vec<_> = self
+            .fields
+            .iter()
+            .map(|(field, value)| (field.as_str(), value.error_sources()))
+            .filter(|(_, sources)| !sources.is_empty())
+            .collect();
+        causes.sort_by_key(|(field, _)| *field);
+        causes
+    }
+
+    // Colored "caused by" lines for every error field with a source chain
+    fn colored_error_cause_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for (field, sources) in self.error_causes() {
+            lines.push(format!(
+                "  {} {}",
+                ansi_color(field, AnsiColor::Cyan),
+                ansi_color("caused by:", AnsiColor::Red)
+            ));
+            for (index, source) in sources.iter().enumerate() {
+                lines.push(format!(
+                    "    {} {}",
+                    ansi_color(&format!("{index}:"), AnsiColor::Rgb(90, 90, 90)),
+                    ansi_color(source, AnsiColor::BrightWhite)
+                ));
+            }
+        }
+        lines
+    }
 }
 
 impl fmt::Display for TraceData {
@@ -155,9 +456,80 @@ impl fmt::Display for TraceData {
     }
 }
 
+thread_local! {
+    // Thread names and ids never change, so they are rendered once per thread
+    static CURRENT_THREAD: (Option<String>, u64) = {
+        let thread = std::thread::current();
+        (thread.name().map(str::to_string), thread_id_to_u64(thread.id()))
+    };
+}
+
+fn current_thread_name() -> Option<String> {
+    CURRENT_THREAD.with(|(name, _)| name.clone())
+}
+
+fn current_thread_id() -> u64 {
+    CURRENT_THREAD.with(|(_, id)| *id)
+}
+
+fn current_task_id() -> Option<u64> {
+    // `task::Id` has no stable numeric accessor, but displays as its number
+    tokio::task::try_id().and_then(|id| id.to_string().parse().ok())
+}
+
+// `ThreadId::as_u64` is unstable; its debug form is `ThreadId(N)`
+fn thread_id_to_u64(id: std::thread::ThreadId) -> u64 {
+    format!("{id:?}")
+        .trim_start_matches("ThreadId(")
+        .trim_end_matches(')')
+        .parse()
+        .unwrap_or(0)
+}
+
+#[cfg(feature = "log")]
+pub(crate) fn log_level_to_tracing(level: log::Level) -> tracing::Level {
+    match level {
+        log::Level::Error => tracing::Level::ERROR,
+        log::Level::Warn => tracing::Level::WARN,
+        log::Level::Info => tracing::Level::INFO,
+        log::Level::Debug => tracing::Level::DEBUG,
+        log::Level::Trace => tracing::Level::TRACE,
+    }
+}
+
+// Collects `log` key-values, keeping primitive values typed
+#[cfg(feature = "log")]
+#[derive(Default)]
+struct LogFieldVisitor {
+    fields: HashMap<String, TraceValue>,
+}
+
+#[cfg(feature = "log")]
+impl<'kvs> log::kv::VisitSource<'kvs> for LogFieldVisitor {
+    fn visit_pair(
+        &mut self,
+        key: log::kv::Key<'kvs>,
+        value: log::kv::Value<'kvs>,
+    ) -> Result<(), log::kv::Error> {
+        let value = if let Some(v) = value.to_bool() {
+            TraceValue::Bool(v)
+        } else if let Some(v) = value.to_i64() {
+            TraceValue::I64(v)
+        } else if let Some(v) = value.to_u64() {
+            TraceValue::U64(v)
+        } else if let Some(v) = value.to_f64() {
+            TraceValue::F64(v)
+        } else {
+            TraceValue::String(value.to_string())
+        };
+        self.fields.insert(key.as_str().to_string(), value);
+        Ok(())
+    }
+}
+
 #[derive(Default)]
 pub struct EventMessageVisitor {
-    pub fields: HashMap<String, String>,
+    pub fields: HashMap<String, TraceValue>,
     pub message: String,
 }
 
@@ -173,7 +545,8 @@ impl tracing::field::Visit for EventMessageVisitor {
         } else {
             let mut buffer = String::new();
             let _ = write!(buffer, "{value:?}");
-            self.fields.insert(field.name().to_string(), buffer);
+            self.fields
+                .insert(field.name().to_string(), TraceValue::Debug(buffer));
         }
     }
 
@@ -184,28 +557,39 @@ impl tracing::field::Visit for EventMessageVisitor {
             }
         } else {
             self.fields
-                .insert(field.name().to_string(), value.to_string());
+                .insert(field.name().to_string(), TraceValue::from(value));
         }
     }
 
     fn record_i64(&mut self, field: &Field, value: i64) {
         self.fields
-            .insert(field.name().to_string(), value.to_string());
+            .insert(field.name().to_string(), TraceValue::I64(value));
     }
 
     fn record_u64(&mut self, field: &Field, value: u64) {
         self.fields
-            .insert(field.name().to_string(), value.to_string());
+            .insert(field.name().to_string(), TraceValue::U64(value));
     }
 
     fn record_bool(&mut self, field: &Field, value: bool) {
         self.fields
-            .insert(field.name().to_string(), value.to_string());
+            .insert(field.name().to_string(), TraceValue::Bool(value));
     }
 
     fn record_f64(&mut self, field: &Field, value: f64) {
         self.fields
-            .insert(field.name().to_string(), value.to_string());
+            .insert(field.name().to_string(), TraceValue::F64(value));
+    }
+
+    fn record_error(&mut self, field: &Field, value: &(dyn std::error::Error + 'static)) {
+        self.fields
+            .insert(field.name().to_string(), TraceValue::from_error(value));
+    }
+
+    #[cfg(tracing_unstable)]
+    fn record_value(&mut self, field: &Field, value: valuable::Value<'_>) {
+        self.fields
+            .insert(field.name().to_string(), TraceValue::from_valuable(value));
     }
 }
 
@@ -283,7 +667,7 @@ impl TraceData {
                     format!(
                         "{}={}",
                         ansi_color(k, AnsiColor::Cyan),
-                        ansi_color(v, AnsiColor::BrightWhite)
+                        ansi_color(&v.to_string(), AnsiColor::BrightWhite)
                     )
                 })
                 .collect::<Vec<_>>()
@@ -292,6 +676,21 @@ impl TraceData {
             formatted.push('}');
         }
 
+        for line in self.colored_error_cause_lines() {
+            formatted.push('\n');
+            formatted.push_str(&line);
+        }
+
+        formatted
+    }
+
+    /// Format with colors and the emitting thread and task
+    pub fn format_colored_with_thread(&self) -> String {
+        let mut formatted = self.format_colored();
+        formatted.push_str(&format!(
+            " {}",
+            ansi_color(&self.thread_label(), AnsiColor::Blue)
+        ));
         formatted
     }
 
@@ -315,7 +714,7 @@ impl TraceData {
                     format!(
                         "{}={}",
                         ansi_color(k, AnsiColor::Cyan),
-                        ansi_color(v, AnsiColor::BrightWhite)
+                        ansi_color(&v.to_string(), AnsiColor::BrightWhite)
                     )
                 })
                 .collect::<Vec<_>>()
@@ -324,6 +723,11 @@ impl TraceData {
             formatted.push('}');
         }
 
+        for line in self.colored_error_cause_lines() {
+            formatted.push('\n');
+            formatted.push_str(&line);
+        }
+
         formatted
     }
 
@@ -420,36 +824,38 @@ impl TraceData {
         }
 
         // Add span hierarchy if present
-        if let Some(hierarchy) = &self.span_hierarchy {
-            if let Some(last_line) = result.last_mut() {
-                last_line.push_str(&format!(
-                    " [Span Hierarchy: {}]",
-                    ansi_color(hierarchy, AnsiColor::BrightMagenta)
-                ));
-            }
+        if let Some(hierarchy) = &self.span_hierarchy
+            && let Some(last_line) = result.last_mut()
+        {
+            last_line.push_str(&format!(
+                " [Span Hierarchy: {}]",
+                ansi_color(hierarchy, AnsiColor::BrightMagenta)
+            ));
         }
 
         // Add fields if present
-        if !self.fields.is_empty() {
-            if let Some(last_line) = result.last_mut() {
-                last_line.push_str(" {");
-                let fields_str = self
-                    .fields
-                    .iter()
-                    .map(|(k, v)| {
-                        format!(
-                            "{}={}",
-                            ansi_color(k, AnsiColor::Cyan),
-                            ansi_color(v, AnsiColor::BrightWhite)
-                        )
-                    })
-                    .collect::<Vec<_>>()
-                    .join(", ");
-                last_line.push_str(&fields_str);
-                last_line.push('}');
-            }
+        if !self.fields.is_empty()
+            && let Some(last_line) = result.last_mut()
+        {
+            last_line.push_str(" {");
+            let fields_str = self
+                .fields
+                .iter()
+                .map(|(k, v)| {
+                    format!(
+                        "{}={}",
+                        ansi_color(k, AnsiColor::Cyan),
+                        ansi_color(&v.to_string(), AnsiColor::BrightWhite)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            last_line.push_str(&fields_str);
+            last_line.push('}');
         }
 
+        result.extend(self.colored_error_cause_lines());
+
         result
     }
 }
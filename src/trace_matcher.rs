@@ -1,19 +1,23 @@
 // src/trace_matcher.rs
-use regex::Regex;
 use serde::{Deserialize, Serialize};
-use tracing::Level;
+use std::sync::Arc;
+use tracing::{Level, Metadata};
 
-use crate::TraceData;
+use crate::{
+    Criterion, TraceData,
+    pattern::{
+        FieldPredicate, MESSAGE_GLOB_PREFIX, MESSAGE_REGEX_PREFIX, MessagePattern, Pattern,
+        SpanFieldPattern, SpanPathPattern, any_matches, compile_all, normalize_field_pattern,
+        normalize_span_field_pattern,
+    },
+};
 
-// Helper function for pattern matching
+// Helper function for pattern matching. Compiles the pattern on every call, so
+// anything matching repeatedly should go through a `Matcher`.
 pub fn matches(pattern: &str, value: &str) -> bool {
-    // Simple glob matching (* as wildcard)
-    let pattern = pattern.replace("*", ".*");
-    if let Ok(re) = Regex::new(&format!("^{pattern}$")) {
-        re.is_match(value)
-    } else {
-        false
-    }
+    // Globs: `*` within a `::` or `/` segment, `**` across them, `?`, `[abc]`, `\`
+    // escapes, and `re:` for a raw regex
+    Pattern::new(pattern).matches(value)
 }
 
 // Define TraceLevel for serialization
@@ -116,38 +120,161 @@ impl From<TraceLevel> for Level {
     }
 }
 
+// How an event's level is compared against `Matcher::level`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LevelMatch {
+    /// The level or anything more severe
+    #[default]
+    AtLeast,
+    /// Only the level itself
+    Exactly,
+    /// The level or anything more verbose
+    AtMost,
+    /// Every level from `Matcher::level` to this one, inclusive, in either order
+    Between(TraceLevel),
+}
+
+/// Patterns are compiled when the matcher is built or deserialized, and again whenever
+/// a builder method changes them. After editing the pattern fields directly, call
+/// `recompile_patterns`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "MatcherFields")]
 pub struct Matcher {
     pub level: TraceLevel,
+    #[serde(default)]
+    pub level_match: LevelMatch,
     pub include: bool,
     /// pub has_module_wildcard: bool, // TODO: Optimize
     pub module_patterns: Vec<String>,
     pub file_patterns: Vec<String>,
     pub span_patterns: Vec<String>,
     pub target_patterns: Vec<String>,
+    /// Matched against the thread name and the numeric thread id
+    #[serde(default)]
+    pub thread_patterns: Vec<String>,
+    /// Predicates on event fields, all of which must hold: `name` (present),
+    /// `name = glob`, `name != glob`, `name ~ regex`, or `name >= 500` with any of
+    /// `>`, `>=`, `<`, `<=`. Nested values are reached with dotted paths.
+    #[serde(default)]
+    pub field_patterns: Vec<String>,
+    /// Predicates on the fields of one span the event is inside, as
+    /// `span{name op value, ...}`: some span with a matching name must satisfy every
+    /// predicate with its own fields, whatever inner spans or the event record
+    #[serde(default)]
+    pub span_field_patterns: Vec<String>,
+    /// Substrings of the event message, or `glob:<glob>` to match the whole message,
+    /// or `regex:<regex>` to search it
+    #[serde(default)]
+    pub message_patterns: Vec<String>,
+    /// Matched against every span the event is inside, not just the innermost one
+    #[serde(default)]
+    pub ancestor_patterns: Vec<String>,
+    /// Matched against the whole span path from the root, e.g. `handle_request::*::db_query`,
+    /// where `*` is one span and `**` any number of spans
+    #[serde(default)]
+    pub span_path_patterns: Vec<String>,
+    #[serde(skip)]
+    compiled: Arc<CompiledPatterns>,
+}
+
+// Serialized form of a `Matcher`, compiled on the way in
+#[derive(Deserialize)]
+struct MatcherFields {
+    level: TraceLevel,
+    #[serde(default)]
+    level_match: LevelMatch,
+    include: bool,
+    module_patterns: Vec<String>,
+    file_patterns: Vec<String>,
+    span_patterns: Vec<String>,
+    target_patterns: Vec<String>,
+    #[serde(default)]
+    thread_patterns: Vec<String>,
+    #[serde(default)]
+    field_patterns: Vec<String>,
+    #[serde(default)]
+    span_field_patterns: Vec<String>,
+    #[serde(default)]
+    message_patterns: Vec<String>,
+    #[serde(default)]
+    ancestor_patterns: Vec<String>,
+    #[serde(default)]
+    span_path_patterns: Vec<String>,
+}
+
+impl From<MatcherFields> for Matcher {
+    fn from(fields: MatcherFields) -> Self {
+        let mut matcher = Self {
+            level: fields.level,
+            level_match: fields.level_match,
+            include: fields.include,
+            module_patterns: fields.module_patterns,
+            file_patterns: fields.file_patterns,
+            span_patterns: fields.span_patterns,
+            target_patterns: fields.target_patterns,
+            thread_patterns: fields.thread_patterns,
+            field_patterns: fields
+                .field_patterns
+                .iter()
+                .map(|pattern| normalize_field_pattern(pattern))
+                .collect(),
+            span_field_patterns: fields
+                .span_field_patterns
+                .iter()
+                .map(|pattern| normalize_span_field_pattern(pattern))
+                .collect(),
+            message_patterns: fields.message_patterns,
+            ancestor_patterns: fields.ancestor_patterns,
+            span_path_patterns: fields.span_path_patterns,
+            compiled: Arc::default(),
+        };
+        matcher.recompile_patterns();
+        matcher
+    }
+}
+
+// Compiled form of every pattern list on a `Matcher`
+#[derive(Default)]
+struct CompiledPatterns {
+    module: Vec<Pattern>,
+    file: Vec<Pattern>,
+    span: Vec<Pattern>,
+    target: Vec<Pattern>,
+    thread: Vec<Pattern>,
+    fields: Vec<FieldPredicate>,
+    span_fields: Vec<SpanFieldPattern>,
+    message: Vec<MessagePattern>,
+    ancestor: Vec<Pattern>,
+    span_path: Vec<SpanPathPattern>,
+}
+
+impl std::fmt::Debug for CompiledPatterns {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompiledPatterns").finish_non_exhaustive()
+    }
 }
 
 impl std::hash::Hash for Matcher {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.include.hash(state);
         self.level.hash(state);
+        self.level_match.hash(state);
         self.module_patterns.hash(state);
         self.file_patterns.hash(state);
         self.span_patterns.hash(state);
         self.target_patterns.hash(state);
+        self.thread_patterns.hash(state);
+        self.field_patterns.hash(state);
+        self.span_field_patterns.hash(state);
+        self.message_patterns.hash(state);
+        self.ancestor_patterns.hash(state);
+        self.span_path_patterns.hash(state);
     }
 }
 
 impl Default for Matcher {
     fn default() -> Self {
-        Self {
-            level: TraceLevel(Level::DEBUG),
-            include: true,
-            module_patterns: vec!["*".to_string()],
-            file_patterns: vec![],
-            span_patterns: vec![],
-            target_patterns: vec![],
-        }
+        Self::debug().all_modules()
     }
 }
 
@@ -155,11 +282,19 @@ impl Eq for Matcher {}
 
 impl PartialEq for Matcher {
     fn eq(&self, other: &Self) -> bool {
-        self.level == other.level
+        self.include == other.include
+            && self.level == other.level
+            && self.level_match == other.level_match
             && self.module_patterns == other.module_patterns
             && self.file_patterns == other.file_patterns
             && self.span_patterns == other.span_patterns
             && self.target_patterns == other.target_patterns
+            && self.thread_patterns == other.thread_patterns
+            && self.field_patterns == other.field_patterns
+            && self.span_field_patterns == other.span_field_patterns
+            && self.message_patterns == other.message_patterns
+            && self.ancestor_patterns == other.ancestor_patterns
+            && self.span_path_patterns == other.span_path_patterns
     }
 }
 
@@ -168,11 +303,19 @@ impl Matcher {
     pub fn new(level: impl Into<TraceLevel>) -> Self {
         Self {
             level: level.into(),
+            level_match: LevelMatch::AtLeast,
             include: true,
             module_patterns: vec![],
             file_patterns: vec![],
             span_patterns: vec![],
             target_patterns: vec![],
+            thread_patterns: vec![],
+            field_patterns: vec![],
+            span_field_patterns: vec![],
+            message_patterns: vec![],
+            ancestor_patterns: vec![],
+            span_path_patterns: vec![],
+            compiled: Arc::default(),
         }
     }
 
@@ -196,6 +339,31 @@ impl Matcher {
         Self::new(Level::ERROR)
     }
 
+    /// Capture every level from `from` to `to`, inclusive
+    pub fn range(from: impl Into<TraceLevel>, to: impl Into<TraceLevel>) -> Self {
+        Self::new(from).level_match(LevelMatch::Between(to.into()))
+    }
+
+    pub fn level_match(mut self, level_match: LevelMatch) -> Self {
+        self.level_match = level_match;
+        self
+    }
+
+    /// Capture the level or anything more severe (the default)
+    pub fn at_least(self) -> Self {
+        self.level_match(LevelMatch::AtLeast)
+    }
+
+    /// Capture only the level itself
+    pub fn exactly(self) -> Self {
+        self.level_match(LevelMatch::Exactly)
+    }
+
+    /// Capture the level or anything more verbose
+    pub fn at_most(self) -> Self {
+        self.level_match(LevelMatch::AtMost)
+    }
+
     // Set inclusion/exclusion
     pub fn include(mut self) -> Self {
         self.include = true;
@@ -212,11 +380,13 @@ impl Matcher {
         patterns: impl IntoIterator<Item = impl Into<String>>,
     ) -> Self {
         self.module_patterns = patterns.into_iter().map(Into::<String>::into).collect();
+        self.recompile_patterns();
         self
     }
 
     pub fn module_pattern(mut self, pattern: impl Into<String>) -> Self {
         self.module_patterns.push(pattern.into());
+        self.recompile_patterns();
         self
     }
 
@@ -226,16 +396,19 @@ impl Matcher {
     ) -> Self {
         self.module_patterns
             .extend(patterns.into_iter().map(Into::<String>::into));
+        self.recompile_patterns();
         self
     }
 
     pub fn file_patterns(mut self, patterns: impl IntoIterator<Item = impl Into<String>>) -> Self {
         self.file_patterns = patterns.into_iter().map(Into::<String>::into).collect();
+        self.recompile_patterns();
         self
     }
 
     pub fn file_pattern(mut self, pattern: impl Into<String>) -> Self {
         self.file_patterns.push(pattern.into());
+        self.recompile_patterns();
         self
     }
 
@@ -245,16 +418,19 @@ impl Matcher {
     ) -> Self {
         self.file_patterns
             .extend(patterns.into_iter().map(Into::<String>::into));
+        self.recompile_patterns();
         self
     }
 
     pub fn span_patterns(mut self, patterns: impl IntoIterator<Item = impl Into<String>>) -> Self {
         self.span_patterns = patterns.into_iter().map(Into::<String>::into).collect();
+        self.recompile_patterns();
         self
     }
 
     pub fn span_pattern(mut self, pattern: impl Into<String>) -> Self {
         self.span_patterns.push(pattern.into());
+        self.recompile_patterns();
         self
     }
 
@@ -264,6 +440,57 @@ impl Matcher {
     ) -> Self {
         self.span_patterns
             .extend(patterns.into_iter().map(Into::<String>::into));
+        self.recompile_patterns();
+        self
+    }
+
+    pub fn ancestor_patterns(
+        mut self,
+        patterns: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.ancestor_patterns = patterns.into_iter().map(Into::<String>::into).collect();
+        self.recompile_patterns();
+        self
+    }
+
+    pub fn ancestor_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.ancestor_patterns.push(pattern.into());
+        self.recompile_patterns();
+        self
+    }
+
+    pub fn extend_ancestor_patterns(
+        mut self,
+        patterns: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.ancestor_patterns
+            .extend(patterns.into_iter().map(Into::<String>::into));
+        self.recompile_patterns();
+        self
+    }
+
+    pub fn span_path_patterns(
+        mut self,
+        patterns: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.span_path_patterns = patterns.into_iter().map(Into::<String>::into).collect();
+        self.recompile_patterns();
+        self
+    }
+
+    pub fn span_path_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.span_path_patterns.push(pattern.into());
+        self.recompile_patterns();
+        self
+    }
+
+    pub fn extend_span_path_patterns(
+        mut self,
+        patterns: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.span_path_patterns
+            .extend(patterns.into_iter().map(Into::<String>::into));
+        self.recompile_patterns();
         self
     }
 
@@ -272,11 +499,13 @@ impl Matcher {
         patterns: impl IntoIterator<Item = impl Into<String>>,
     ) -> Self {
         self.target_patterns = patterns.into_iter().map(Into::<String>::into).collect();
+        self.recompile_patterns();
         self
     }
 
     pub fn target_pattern(mut self, pattern: impl Into<String>) -> Self {
         self.target_patterns.push(pattern.into());
+        self.recompile_patterns();
         self
     }
 
@@ -286,167 +515,527 @@ impl Matcher {
     ) -> Self {
         self.target_patterns
             .extend(patterns.into_iter().map(Into::<String>::into));
+        self.recompile_patterns();
+        self
+    }
+
+    pub fn thread_patterns(
+        mut self,
+        patterns: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.thread_patterns = patterns.into_iter().map(Into::<String>::into).collect();
+        self.recompile_patterns();
+        self
+    }
+
+    pub fn thread_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.thread_patterns.push(pattern.into());
+        self.recompile_patterns();
+        self
+    }
+
+    pub fn extend_thread_patterns(
+        mut self,
+        patterns: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.thread_patterns
+            .extend(patterns.into_iter().map(Into::<String>::into));
+        self.recompile_patterns();
+        self
+    }
+
+    /// Field patterns are stored as `name op value`, however they are spaced
+    pub fn field_patterns(mut self, patterns: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.field_patterns = patterns
+            .into_iter()
+            .map(|pattern| normalize_field_pattern(&pattern.into()))
+            .collect();
+        self.recompile_patterns();
+        self
+    }
+
+    pub fn field_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.field_patterns
+            .push(normalize_field_pattern(&pattern.into()));
+        self.recompile_patterns();
+        self
+    }
+
+    pub fn extend_field_patterns(
+        mut self,
+        patterns: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.field_patterns.extend(
+            patterns
+                .into_iter()
+                .map(|pattern| normalize_field_pattern(&pattern.into())),
+        );
+        self.recompile_patterns();
+        self
+    }
+
+    /// Span field patterns are stored as `span{name op value, ...}`, however they are spaced
+    pub fn span_field_patterns(
+        mut self,
+        patterns: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.span_field_patterns = patterns
+            .into_iter()
+            .map(|pattern| normalize_span_field_pattern(&pattern.into()))
+            .collect();
+        self.recompile_patterns();
+        self
+    }
+
+    pub fn span_field_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.span_field_patterns
+            .push(normalize_span_field_pattern(&pattern.into()));
+        self.recompile_patterns();
+        self
+    }
+
+    pub fn extend_span_field_patterns(
+        mut self,
+        patterns: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.span_field_patterns.extend(
+            patterns
+                .into_iter()
+                .map(|pattern| normalize_span_field_pattern(&pattern.into())),
+        );
+        self.recompile_patterns();
         self
     }
 
+    pub fn message_patterns(
+        mut self,
+        patterns: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.message_patterns = patterns.into_iter().map(Into::<String>::into).collect();
+        self.recompile_patterns();
+        self
+    }
+
+    pub fn message_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.message_patterns.push(pattern.into());
+        self.recompile_patterns();
+        self
+    }
+
+    pub fn extend_message_patterns(
+        mut self,
+        patterns: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.message_patterns
+            .extend(patterns.into_iter().map(Into::<String>::into));
+        self.recompile_patterns();
+        self
+    }
+
+    /// Match messages containing `text`
+    pub fn message_contains(self, text: impl Into<String>) -> Self {
+        self.message_pattern(text)
+    }
+
+    /// Match messages that fit `glob` as a whole
+    pub fn message_glob(self, glob: impl AsRef<str>) -> Self {
+        self.message_pattern(format!("{MESSAGE_GLOB_PREFIX}{}", glob.as_ref()))
+    }
+
+    /// Match messages in which `regex` finds a match
+    pub fn message_regex(self, regex: impl AsRef<str>) -> Self {
+        self.message_pattern(format!("{MESSAGE_REGEX_PREFIX}{}", regex.as_ref()))
+    }
+
     // Shorthand for common patterns
     pub fn all_modules(mut self) -> Self {
         self.module_patterns.push("*".to_string());
+        self.recompile_patterns();
         self
     }
 
+    /// Recompile the patterns, e.g. after editing the pattern fields directly
+    pub fn recompile_patterns(&mut self) {
+        self.compiled = Arc::new(CompiledPatterns {
+            module: compile_all(&self.module_patterns),
+            file: compile_all(&self.file_patterns),
+            span: compile_all(&self.span_patterns),
+            target: compile_all(&self.target_patterns),
+            thread: compile_all(&self.thread_patterns),
+            fields: self
+                .field_patterns
+                .iter()
+                .map(|pattern| FieldPredicate::new(pattern))
+                .collect(),
+            span_fields: self
+                .span_field_patterns
+                .iter()
+                .map(|pattern| SpanFieldPattern::new(pattern))
+                .collect(),
+            message: self
+                .message_patterns
+                .iter()
+                .map(|pattern| MessagePattern::new(pattern))
+                .collect(),
+            ancestor: compile_all(&self.ancestor_patterns),
+            span_path: self
+                .span_path_patterns
+                .iter()
+                .map(|pattern| SpanPathPattern::new(pattern))
+                .collect(),
+        });
+    }
+
+    /// Check that every pattern compiled. Invalid patterns never match, so a matcher
+    /// built from user input should be validated before use.
+    pub fn validate(&self) -> Result<(), Vec<PatternError>> {
+        let compiled = &*self.compiled;
+        let globs = [
+            (Criterion::Module, &self.module_patterns, &compiled.module),
+            (Criterion::File, &self.file_patterns, &compiled.file),
+            (Criterion::Span, &self.span_patterns, &compiled.span),
+            (Criterion::Target, &self.target_patterns, &compiled.target),
+            (Criterion::Thread, &self.thread_patterns, &compiled.thread),
+            (
+                Criterion::Ancestor,
+                &self.ancestor_patterns,
+                &compiled.ancestor,
+            ),
+        ];
+
+        // Compiled patterns line up with their sources
+        let mut errors = Vec::new();
+        let mut check = |criterion, pattern: &String, error: Option<&str>| {
+            if let Some(reason) = error {
+                errors.push(PatternError {
+                    criterion,
+                    pattern: pattern.clone(),
+                    reason: reason.to_string(),
+                });
+            }
+        };
+        for (criterion, patterns, compiled) in globs {
+            for (pattern, compiled) in patterns.iter().zip(compiled) {
+                check(criterion, pattern, compiled.error());
+            }
+        }
+        for (pattern, compiled) in self.span_path_patterns.iter().zip(&compiled.span_path) {
+            check(Criterion::SpanPath, pattern, compiled.error());
+        }
+        for (pattern, compiled) in self.message_patterns.iter().zip(&compiled.message) {
+            check(Criterion::Message, pattern, compiled.error());
+        }
+        for (index, (pattern, compiled)) in
+            self.field_patterns.iter().zip(&compiled.fields).enumerate()
+        {
+            check(Criterion::Field(index), pattern, compiled.error());
+        }
+        for (index, (pattern, compiled)) in self
+            .span_field_patterns
+            .iter()
+            .zip(&compiled.span_fields)
+            .enumerate()
+        {
+            check(Criterion::SpanField(index), pattern, compiled.error());
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
     pub fn into_matcher_set(self) -> MatcherSet {
         MatcherSet::from_matcher(self)
     }
 
+    /// Whether an event at `level` passes this matcher's level criterion
+    pub fn matches_level(&self, level: Level) -> bool {
+        // More verbose levels compare greater
+        match self.level_match {
+            LevelMatch::AtLeast => level <= self.level.0,
+            LevelMatch::Exactly => level == self.level.0,
+            LevelMatch::AtMost => level >= self.level.0,
+            LevelMatch::Between(other) => {
+                let (severe, verbose) = if other.0 < self.level.0 {
+                    (other.0, self.level.0)
+                } else {
+                    (self.level.0, other.0)
+                };
+                severe <= level && level <= verbose
+            }
+        }
+    }
+
+    /// The most verbose level this matcher can capture
+    pub fn max_level(&self) -> Level {
+        match self.level_match {
+            LevelMatch::AtLeast | LevelMatch::Exactly => self.level.0,
+            LevelMatch::AtMost => Level::TRACE,
+            LevelMatch::Between(other) => self.level.0.max(other.0),
+        }
+    }
+
+    /// Whether events from a callsite could match, judging only by what its static
+    /// metadata knows (level, module and target). Used to pre-filter callsites, so it
+    /// must never return `false` for an event `matches` would accept.
+    pub fn matches_metadata(&self, metadata: &Metadata<'_>) -> bool {
+        if !self.matches_level(*metadata.level()) {
+            return false;
+        }
+        let compiled = &*self.compiled;
+
+        // Events without a module may still inherit one from their span
+        if let Some(module_path) = metadata.module_path()
+            && !compiled.module.is_empty()
+            && !any_matches(&compiled.module, module_path)
+        {
+            return false;
+        }
+
+        // Events with a generic target may still inherit one from their span
+        let target = metadata.target();
+        if !target.is_empty()
+            && target != "unknown"
+            && !compiled.target.is_empty()
+            && !any_matches(&compiled.target, target)
+        {
+            return false;
+        }
+
+        true
+    }
+
     pub fn matches(&self, event: &TraceData) -> bool {
+        self.failed_criterion(event).is_none()
+    }
+
+    /// The first criterion the event does not meet, or `None` when the matcher matches
+    pub fn failed_criterion(&self, event: &TraceData) -> Option<Criterion> {
         // Check level first
-        match self.level.0 {
-            Level::ERROR => {
-                if event.level.0 != Level::ERROR {
-                    return false;
-                }
-            }
-            Level::WARN => {
-                if !matches!(event.level.0, Level::ERROR | Level::WARN) {
-                    return false;
-                }
-            }
-            Level::INFO => {
-                if !matches!(event.level.0, Level::ERROR | Level::WARN | Level::INFO) {
-                    return false;
-                }
-            }
-            Level::DEBUG => {
-                if !matches!(
-                    event.level.0,
-                    Level::ERROR | Level::WARN | Level::INFO | Level::DEBUG
-                ) {
-                    return false;
-                }
-            }
-            Level::TRACE => {} // All levels pass
+        if !self.matches_level(event.level.0) {
+            return Some(Criterion::Level);
         }
+        let compiled = &*self.compiled;
 
         // Check module path
         if let Some(module_path) = &event.module_path {
             // If we have include patterns, at least one must match
-            if !self.module_patterns.is_empty() {
-                let mut module_matched = false;
-                for pattern in &self.module_patterns {
-                    if matches(pattern, module_path) {
-                        module_matched = true;
-                        break;
-                    }
-                }
-                if !module_matched {
-                    return false;
-                }
+            if !compiled.module.is_empty() && !any_matches(&compiled.module, module_path) {
+                return Some(Criterion::Module);
             }
-        } else if !self.module_patterns.is_empty() {
+        } else if !compiled.module.is_empty() {
             // Special case: if there's a wildcard pattern, allow no-module events
-            let has_wildcard = self.module_patterns.iter().any(|p| p == "*");
+            let has_wildcard = compiled
+                .module
+                .iter()
+                .any(|pattern| matches!(pattern, Pattern::Any));
             if !has_wildcard {
                 // If we require a specific module pattern but there's no module path, exclude
-                return false;
+                return Some(Criterion::Module);
             }
         }
 
         // Check file path
         // If we have include patterns, at least one must match
-        if !self.file_patterns.is_empty() {
-            let mut file_matched = false;
-            if let Some(file) = &event.file {
-                for pattern in &self.file_patterns {
-                    if matches(pattern, file) {
-                        file_matched = true;
-                        break;
-                    }
-                }
-            }
+        if !compiled.file.is_empty() {
+            let file_matched = event
+                .file
+                .as_deref()
+                .is_some_and(|file| any_matches(&compiled.file, file));
             if !file_matched {
-                return false;
+                return Some(Criterion::File);
             }
         }
 
         // Check span name
-        if !self.span_patterns.is_empty() {
-            let mut span_matched = false;
-            if let Some(span_name) = &event.span_name {
-                for pattern in &self.span_patterns {
-                    if matches(pattern, span_name) {
-                        span_matched = true;
-                        break;
-                    }
-                }
-            }
+        if !compiled.span.is_empty() {
+            let span_matched = event
+                .span_name
+                .as_deref()
+                .is_some_and(|span_name| any_matches(&compiled.span, span_name));
             if !span_matched {
-                return false;
+                return Some(Criterion::Span);
             }
         }
 
-        // Check target
-        if !self.target_patterns.is_empty() {
-            let mut target_matched = false;
-            for pattern in &self.target_patterns {
-                if matches(pattern, &event.target) {
-                    target_matched = true;
-                    break;
-                }
+        // Check the spans the event is inside
+        if !compiled.ancestor.is_empty() || !compiled.span_path.is_empty() {
+            let path = span_path(event);
+            if !compiled.ancestor.is_empty()
+                && !path
+                    .iter()
+                    .any(|span_name| any_matches(&compiled.ancestor, span_name))
+            {
+                return Some(Criterion::Ancestor);
             }
-            if !target_matched {
-                return false;
+            if !compiled.span_path.is_empty()
+                && !compiled
+                    .span_path
+                    .iter()
+                    .any(|pattern| pattern.matches(&path))
+            {
+                return Some(Criterion::SpanPath);
             }
         }
 
-        true
+        // Check target
+        if !compiled.target.is_empty() && !any_matches(&compiled.target, &event.target) {
+            return Some(Criterion::Target);
+        }
+
+        // Check thread name or id
+        if !compiled.thread.is_empty() {
+            let name_matched = event
+                .thread_name
+                .as_deref()
+                .is_some_and(|name| any_matches(&compiled.thread, name));
+            if !name_matched && !any_matches(&compiled.thread, &event.thread_id.to_string()) {
+                return Some(Criterion::Thread);
+            }
+        }
+
+        // Check message text
+        if !compiled.message.is_empty()
+            && !compiled
+                .message
+                .iter()
+                .any(|pattern| pattern.matches(&event.message))
+        {
+            return Some(Criterion::Message);
+        }
+
+        // Check fields, every predicate must hold
+        if let Some(index) = compiled
+            .fields
+            .iter()
+            .position(|predicate| !predicate.matches(&event.fields))
+        {
+            return Some(Criterion::Field(index));
+        }
+
+        // Check the fields of the spans themselves, each pattern on its own span
+        compiled
+            .span_fields
+            .iter()
+            .position(|pattern| !pattern.matches(&event.spans))
+            .map(Criterion::SpanField)
     }
 }
 
+// Names of the spans an event is inside, root first. Events assembled by hand may
+// only carry the joined hierarchy.
+fn span_path(event: &TraceData) -> Vec<&str> {
+    if !event.spans.is_empty() {
+        return event.spans.iter().map(|span| span.name.as_str()).collect();
+    }
+    event
+        .span_hierarchy
+        .as_deref()
+        .map(|hierarchy| hierarchy.split("::").collect())
+        .unwrap_or_default()
+}
+
 impl From<Matcher> for MatcherSet {
     fn from(val: Matcher) -> Self {
         val.into_matcher_set()
     }
 }
 
+/// A pattern that did not compile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternError {
+    /// Which list the pattern is in
+    pub criterion: Criterion,
+    pub pattern: String,
+    pub reason: String,
+}
+
+impl std::fmt::Display for PatternError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid {} pattern `{}`: {}",
+            self.criterion, self.pattern, self.reason
+        )
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+// How a `MatcherSet` decides between its matchers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MatchOrder {
+    /// Any matching exclude silences the event, otherwise any matching include captures it
+    #[default]
+    ExcludesFirst,
+    /// Matchers are tried in the order they were added and the first match decides
+    FirstMatch,
+}
+
+/// Matchers keep the order they were added in. Adding a matcher equal to one already
+/// in the set replaces it in place.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "MatcherSetFields")]
 pub struct MatcherSet {
-    matchers: std::collections::HashSet<Matcher>,
+    matchers: Vec<Matcher>,
+    #[serde(default)]
+    order: MatchOrder,
+}
+
+#[derive(Deserialize)]
+struct MatcherSetFields {
+    matchers: Vec<Matcher>,
+    #[serde(default)]
+    order: MatchOrder,
+}
+
+impl From<MatcherSetFields> for MatcherSet {
+    fn from(fields: MatcherSetFields) -> Self {
+        Self::from_matchers(fields.matchers).with_order(fields.order)
+    }
 }
 
 // Add builder methods for TraceFilterSet as well
 impl MatcherSet {
     pub fn empty() -> Self {
         Self {
-            matchers: std::collections::HashSet::new(),
+            matchers: Vec::new(),
+            order: MatchOrder::default(),
         }
     }
 
     pub fn from_matcher(matcher: Matcher) -> Self {
         let mut filter = Self::empty();
-        filter.matchers.insert(matcher);
+        filter.add_matcher(matcher);
         filter
     }
 
     pub fn from_matchers(matchers: impl IntoIterator<Item = Matcher>) -> Self {
         let mut filter = Self::empty();
         for matcher in matchers {
-            filter.matchers.insert(matcher);
+            filter.add_matcher(matcher);
         }
         filter
     }
 
     pub fn with_matcher(mut self, filter: Matcher) -> Self {
-        self.matchers.replace(filter);
+        self.add_matcher(filter);
         self
     }
 
     pub fn add_matcher(&mut self, filter: Matcher) {
-        self.matchers.replace(filter);
+        match self.matchers.iter_mut().find(|matcher| **matcher == filter) {
+            Some(existing) => *existing = filter,
+            None => self.matchers.push(filter),
+        }
     }
 
     pub fn remove_matcher(&mut self, filter: &Matcher) -> bool {
-        self.matchers.remove(filter)
+        let len = self.matchers.len();
+        self.matchers.retain(|matcher| matcher != filter);
+        self.matchers.len() != len
     }
 
     pub fn clear_matchers(&mut self) {
@@ -457,7 +1046,58 @@ impl MatcherSet {
         self.matchers.is_empty()
     }
 
+    /// Matchers in the order they were added
     pub fn iter_matchers(&self) -> Vec<&Matcher> {
         self.matchers.iter().collect()
     }
+
+    pub fn with_order(mut self, order: MatchOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Evaluate matchers top to bottom, letting the first match decide
+    pub fn first_match(self) -> Self {
+        self.with_order(MatchOrder::FirstMatch)
+    }
+
+    pub fn set_order(&mut self, order: MatchOrder) {
+        self.order = order;
+    }
+
+    pub fn order(&self) -> MatchOrder {
+        self.order
+    }
+
+    /// Check every matcher's patterns, collecting all errors
+    pub fn validate(&self) -> Result<(), Vec<PatternError>> {
+        let errors: Vec<_> = self
+            .matchers
+            .iter()
+            .filter_map(|matcher| matcher.validate().err())
+            .flatten()
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// The matcher that decides what happens to an event: captured when it is an
+    /// include, silenced when it is an exclude. `None` means no matcher applies.
+    pub fn evaluate(&self, event: &TraceData) -> Option<&Matcher> {
+        match self.order {
+            MatchOrder::ExcludesFirst => self
+                .matchers
+                .iter()
+                .find(|matcher| !matcher.include && matcher.matches(event))
+                .or_else(|| {
+                    self.matchers
+                        .iter()
+                        .find(|matcher| matcher.include && matcher.matches(event))
+                }),
+            MatchOrder::FirstMatch => self.matchers.iter().find(|matcher| matcher.matches(event)),
+        }
+    }
 }
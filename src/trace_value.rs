@@ -0,0 +1,526 @@
+// src/trace_value.rs
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{MapAccess, SeqAccess, Visitor},
+    ser::{SerializeMap, SerializeSeq},
+};
+use std::fmt;
+use valuable::{NamedValues, Valuable, Value};
+
+// Typed value of a field recorded on an event or span
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceValue {
+    String(String),
+    I64(i64),
+    U64(u64),
+    F64(f64),
+    Bool(bool),
+    /// Value that was only available through its `Debug` representation
+    Debug(String),
+    /// Lists, sets and tuples
+    List(Vec<TraceValue>),
+    /// Map entries in iteration order, with keys rendered to strings
+    Map(Vec<(String, TraceValue)>),
+    /// Struct fields in declaration order; tuple structs use "0", "1", ... as names
+    Struct {
+        name: String,
+        fields: Vec<(String, TraceValue)>,
+    },
+    /// Enum variant with its fields, named like `Struct` fields
+    Enum {
+        name: String,
+        variant: String,
+        fields: Vec<(String, TraceValue)>,
+    },
+    /// Error with its display message and the messages of its `source()` chain
+    Error {
+        message: String,
+        sources: Vec<String>,
+    },
+    /// `()` or `None`
+    Unit,
+}
+
+impl TraceValue {
+    /// Text of a string or debug value, or the message of an error
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            TraceValue::String(s) | TraceValue::Debug(s) | TraceValue::Error { message: s, .. } => {
+                Some(s)
+            }
+            _ => None,
+        }
+    }
+
+    /// Capture an error with the messages of its whole `source()` chain
+    pub fn from_error(error: &(dyn std::error::Error + 'static)) -> Self {
+        let mut sources = Vec::new();
+        let mut source = error.source();
+        while let Some(cause) = source {
+            sources.push(cause.to_string());
+            source = cause.source();
+        }
+
+        TraceValue::Error {
+            message: error.to_string(),
+            sources,
+        }
+    }
+
+    /// Messages of an error's `source()` chain, outermost first
+    pub fn error_sources(&self) -> &[String] {
+        match self {
+            TraceValue::Error { sources, .. } => sources,
+            _ => &[],
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            TraceValue::I64(v) => Some(v),
+            TraceValue::U64(v) => i64::try_from(v).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            TraceValue::U64(v) => Some(v),
+            TraceValue::I64(v) => u64::try_from(v).ok(),
+            _ => None,
+        }
+    }
+
+    /// Numeric value of any integer or float variant, for comparisons
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            TraceValue::I64(v) => Some(v as f64),
+            TraceValue::U64(v) => Some(v as f64),
+            TraceValue::F64(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            TraceValue::Bool(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        self.as_f64().is_some()
+    }
+
+    /// Look up a named field of a struct, enum or map value
+    pub fn get(&self, key: &str) -> Option<&TraceValue> {
+        match self {
+            TraceValue::Map(entries)
+            | TraceValue::Struct {
+                fields: entries, ..
+            }
+            | TraceValue::Enum {
+                fields: entries, ..
+            } => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Capture a `valuable` value, preserving its nested structure
+    ///
+    /// Events only hand values over this way when `tracing` is built with
+    /// `--cfg tracing_unstable`. This crate's `.cargo/config.toml` does not reach
+    /// downstream crates, so applications must set `RUSTFLAGS="--cfg tracing_unstable"`
+    /// themselves, or `tracing` won't accept `valuable` values as fields at all.
+    pub fn from_valuable(value: Value<'_>) -> Self {
+        match value {
+            Value::Bool(v) => TraceValue::Bool(v),
+            Value::Char(v) => TraceValue::String(v.to_string()),
+            Value::F32(v) => TraceValue::F64(v.into()),
+            Value::F64(v) => TraceValue::F64(v),
+            Value::I8(v) => TraceValue::I64(v.into()),
+            Value::I16(v) => TraceValue::I64(v.into()),
+            Value::I32(v) => TraceValue::I64(v.into()),
+            Value::I64(v) => TraceValue::I64(v),
+            Value::Isize(v) => TraceValue::I64(v as i64),
+            Value::I128(v) => i64::try_from(v)
+                .map(TraceValue::I64)
+                .unwrap_or_else(|_| TraceValue::Debug(v.to_string())),
+            Value::U8(v) => TraceValue::U64(v.into()),
+            Value::U16(v) => TraceValue::U64(v.into()),
+            Value::U32(v) => TraceValue::U64(v.into()),
+            Value::U64(v) => TraceValue::U64(v),
+            Value::Usize(v) => TraceValue::U64(v as u64),
+            Value::U128(v) => u64::try_from(v)
+                .map(TraceValue::U64)
+                .unwrap_or_else(|_| TraceValue::Debug(v.to_string())),
+            Value::String(v) => TraceValue::String(v.to_string()),
+            Value::Path(v) => TraceValue::String(v.display().to_string()),
+            Value::Error(v) => TraceValue::from_error(v),
+            Value::Listable(v) => TraceValue::List(ValuableCollector::collect(v).items),
+            Value::Tuplable(v) => TraceValue::List(ValuableCollector::collect(v).into_items()),
+            Value::Mappable(v) => TraceValue::Map(ValuableCollector::collect(v).entries),
+            Value::Structable(v) => TraceValue::Struct {
+                name: v.definition().name().to_string(),
+                fields: ValuableCollector::collect(v).entries,
+            },
+            Value::Enumerable(v) => TraceValue::Enum {
+                name: v.definition().name().to_string(),
+                variant: v.variant().name().to_string(),
+                fields: ValuableCollector::collect(v).entries,
+            },
+            Value::Unit => TraceValue::Unit,
+            _ => TraceValue::Debug(format!("{value:?}")),
+        }
+    }
+
+    // Nested values quote their strings so `{"a": "1"}` and `{"a": 1}` stay distinct
+    fn fmt_nested(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceValue::String(s) => write!(f, "{s:?}"),
+            other => write!(f, "{other}"),
+        }
+    }
+}
+
+// Writes `a: 1, b: 2` or, for positional fields, `1, 2`
+fn fmt_fields(
+    f: &mut fmt::Formatter<'_>,
+    fields: &[(String, TraceValue)],
+    named: bool,
+) -> fmt::Result {
+    for (i, (key, value)) in fields.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        if named {
+            write!(f, "{key}: ")?;
+        }
+        value.fmt_nested(f)?;
+    }
+    Ok(())
+}
+
+// Tuple structs and tuple variants record their fields as "0", "1", ...
+fn is_positional(fields: &[(String, TraceValue)]) -> bool {
+    !fields.is_empty() && fields.iter().all(|(key, _)| key.parse::<usize>().is_ok())
+}
+
+impl fmt::Display for TraceValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceValue::String(s) | TraceValue::Debug(s) => f.write_str(s),
+            TraceValue::Error { message, .. } => f.write_str(message),
+            TraceValue::I64(v) => write!(f, "{v}"),
+            TraceValue::U64(v) => write!(f, "{v}"),
+            TraceValue::F64(v) => write!(f, "{v}"),
+            TraceValue::Bool(v) => write!(f, "{v}"),
+            TraceValue::Unit => f.write_str("()"),
+            TraceValue::List(items) => {
+                f.write_str("[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    item.fmt_nested(f)?;
+                }
+                f.write_str("]")
+            }
+            TraceValue::Map(entries) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{key:?}: ")?;
+                    value.fmt_nested(f)?;
+                }
+                f.write_str("}")
+            }
+            TraceValue::Struct { name, fields } => {
+                f.write_str(name)?;
+                fmt_body(f, fields)
+            }
+            TraceValue::Enum {
+                name,
+                variant,
+                fields,
+            } => {
+                write!(f, "{name}::{variant}")?;
+                fmt_body(f, fields)
+            }
+        }
+    }
+}
+
+// Writes ` { a: 1 }`, `(1, 2)` or nothing for unit-like structs and variants
+fn fmt_body(f: &mut fmt::Formatter<'_>, fields: &[(String, TraceValue)]) -> fmt::Result {
+    if fields.is_empty() {
+        Ok(())
+    } else if is_positional(fields) {
+        f.write_str("(")?;
+        fmt_fields(f, fields, false)?;
+        f.write_str(")")
+    } else {
+        f.write_str(" { ")?;
+        fmt_fields(f, fields, true)?;
+        f.write_str(" }")
+    }
+}
+
+// Collects the children of a nested `valuable` value
+#[derive(Default)]
+struct ValuableCollector {
+    items: Vec<TraceValue>,
+    entries: Vec<(String, TraceValue)>,
+}
+
+impl ValuableCollector {
+    fn collect(value: &dyn Valuable) -> Self {
+        let mut collector = Self::default();
+        value.visit(&mut collector);
+        collector
+    }
+
+    // Positional fields (tuples) flattened into list items
+    fn into_items(mut self) -> Vec<TraceValue> {
+        self.items
+            .extend(self.entries.into_iter().map(|(_, value)| value));
+        self.items
+    }
+}
+
+impl valuable::Visit for ValuableCollector {
+    fn visit_value(&mut self, value: Value<'_>) {
+        self.items.push(TraceValue::from_valuable(value));
+    }
+
+    fn visit_named_fields(&mut self, named_values: &NamedValues<'_>) {
+        for (field, value) in named_values {
+            self.entries
+                .push((field.name().to_string(), TraceValue::from_valuable(*value)));
+        }
+    }
+
+    fn visit_unnamed_fields(&mut self, values: &[Value<'_>]) {
+        for (i, value) in values.iter().enumerate() {
+            self.entries
+                .push((i.to_string(), TraceValue::from_valuable(*value)));
+        }
+    }
+
+    fn visit_entry(&mut self, key: Value<'_>, value: Value<'_>) {
+        let key = TraceValue::from_valuable(key).to_string();
+        self.entries.push((key, TraceValue::from_valuable(value)));
+    }
+}
+
+// Serialized the way serde would serialize the original value: primitives as
+// themselves, structs and maps as objects, enums externally tagged
+impl Serialize for TraceValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            TraceValue::String(s) | TraceValue::Debug(s) => serializer.serialize_str(s),
+            TraceValue::I64(v) => serializer.serialize_i64(*v),
+            TraceValue::U64(v) => serializer.serialize_u64(*v),
+            TraceValue::F64(v) => serializer.serialize_f64(*v),
+            TraceValue::Bool(v) => serializer.serialize_bool(*v),
+            TraceValue::Unit => serializer.serialize_unit(),
+            TraceValue::Error { message, sources } => {
+                let mut map = serializer.serialize_map(Some(2))?;
+                map.serialize_entry("message", message)?;
+                map.serialize_entry("sources", sources)?;
+                map.end()
+            }
+            TraceValue::List(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            TraceValue::Map(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+            TraceValue::Struct { fields, .. } => SerializeFields(fields).serialize(serializer),
+            TraceValue::Enum {
+                variant, fields, ..
+            } => {
+                if fields.is_empty() {
+                    serializer.serialize_str(variant)
+                } else {
+                    let mut map = serializer.serialize_map(Some(1))?;
+                    map.serialize_entry(variant, &SerializeFields(fields))?;
+                    map.end()
+                }
+            }
+        }
+    }
+}
+
+// Named fields as an object, positional fields as an array
+struct SerializeFields<'a>(&'a [(String, TraceValue)]);
+
+impl Serialize for SerializeFields<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if is_positional(self.0) {
+            let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+            for (_, value) in self.0 {
+                seq.serialize_element(value)?;
+            }
+            seq.end()
+        } else {
+            let mut map = serializer.serialize_map(Some(self.0.len()))?;
+            for (key, value) in self.0 {
+                map.serialize_entry(key, value)?;
+            }
+            map.end()
+        }
+    }
+}
+
+// Deserialized from the self-describing shape: objects become maps and arrays
+// become lists, since struct and enum names are not part of the serialized form.
+// The round trip is lossy: `U64` values that fit come back as `I64`, `Debug` as
+// `String`, errors and structs as `Map`, and enums as `String` or `Map`.
+impl<'de> Deserialize<'de> for TraceValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(TraceValueVisitor)
+    }
+}
+
+struct TraceValueVisitor;
+
+impl<'de> Visitor<'de> for TraceValueVisitor {
+    type Value = TraceValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a trace field value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<TraceValue, E> {
+        Ok(TraceValue::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<TraceValue, E> {
+        Ok(TraceValue::I64(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<TraceValue, E> {
+        // Formats don't tell the two apart, so anything that fits comes back signed and
+        // `U64(5)` reads back as `I64(5)`, which doesn't compare equal
+        Ok(i64::try_from(v)
+            .map(TraceValue::I64)
+            .unwrap_or(TraceValue::U64(v)))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<TraceValue, E> {
+        Ok(TraceValue::F64(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<TraceValue, E> {
+        Ok(TraceValue::String(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<TraceValue, E> {
+        Ok(TraceValue::String(v))
+    }
+
+    fn visit_unit<E>(self) -> Result<TraceValue, E> {
+        Ok(TraceValue::Unit)
+    }
+
+    fn visit_none<E>(self) -> Result<TraceValue, E> {
+        Ok(TraceValue::Unit)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<TraceValue, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        TraceValue::deserialize(deserializer)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<TraceValue, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(TraceValue::List(items))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<TraceValue, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut entries = Vec::new();
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(TraceValue::Map(entries))
+    }
+}
+
+impl From<String> for TraceValue {
+    fn from(value: String) -> Self {
+        TraceValue::String(value)
+    }
+}
+
+impl From<&str> for TraceValue {
+    fn from(value: &str) -> Self {
+        TraceValue::String(value.to_string())
+    }
+}
+
+impl From<i64> for TraceValue {
+    fn from(value: i64) -> Self {
+        TraceValue::I64(value)
+    }
+}
+
+impl From<u64> for TraceValue {
+    fn from(value: u64) -> Self {
+        TraceValue::U64(value)
+    }
+}
+
+impl From<f64> for TraceValue {
+    fn from(value: f64) -> Self {
+        TraceValue::F64(value)
+    }
+}
+
+impl From<bool> for TraceValue {
+    fn from(value: bool) -> Self {
+        TraceValue::Bool(value)
+    }
+}
+
+impl PartialEq<str> for TraceValue {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == Some(other)
+    }
+}
+
+impl PartialEq<&str> for TraceValue {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == Some(*other)
+    }
+}
@@ -1,11 +1,21 @@
 // src/tracer.rs
 use anyhow::{Context, Result};
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    backtrace::{Backtrace, BacktraceStatus},
+    collections::HashMap,
+    sync::{Arc, Mutex, TryLockError, Weak, atomic::Ordering},
+};
 use tokio::sync::{mpsc, oneshot};
+use tracing::{Dispatch, subscriber::DefaultGuard};
 
+#[cfg(feature = "layer")]
+use crate::TracerLayer;
+#[cfg(feature = "log")]
+use crate::TracerLogger;
 use crate::{
-    DispatcherCommand, MatcherSet, ResultSender, TraceCounters, TraceEvent, TracerConfig,
-    TracingDispatcher, TracingSubscriber,
+    CallsiteFilter, DispatcherCommand, DispatcherState, EventSender, Explanation, MatcherSet,
+    ResultSender, SpanEvents, TraceCounters, TraceData, TraceEvent, TracerConfig,
+    TracingDispatcher, TracingSubscriber, event_queue, is_dispatching, lock_state,
 };
 
 pub type EventCallback = Arc<dyn Fn(TraceEvent, &[&str]) + Send + Sync>;
@@ -13,9 +23,12 @@ pub type SilencedEventCallback = Arc<dyn Fn(TraceEvent, &[&str]) + Send + Sync>;
 pub type DroppedEventCallback = Arc<dyn Fn(TraceEvent) + Send + Sync>;
 
 pub struct Tracer {
-    event_tx: mpsc::UnboundedSender<TraceEvent>,
+    event_tx: EventSender,
     command_tx: mpsc::UnboundedSender<DispatcherCommand>,
     counters: TraceCounters,
+    span_events: SpanEvents,
+    callsite_filter: Arc<CallsiteFilter>,
+    dispatcher_state: Weak<Mutex<DispatcherState>>,
 }
 
 impl Tracer {
@@ -29,8 +42,7 @@ impl Tracer {
         let tracer = Self::new_with_config(config);
 
         // Create our custom subscriber
-        let subscriber =
-            TracingSubscriber::new(tracer.event_tx.clone(), tracer.counters.event_id.clone());
+        let subscriber = tracer.subscriber();
 
         // Set the global default subscriber
         tracing::subscriber::set_global_default(subscriber)
@@ -39,11 +51,26 @@ impl Tracer {
         Ok(tracer)
     }
 
+    /// Initialize tracing with the provided config as the default for the current
+    /// thread only, until the returned guard is dropped. Unlike `init` this can be
+    /// used any number of times, so independent tracers can coexist in one process.
+    pub fn scoped(config: TracerConfig) -> (Self, DefaultGuard) {
+        let tracer = Self::new_with_config(config);
+        let guard = tracing::dispatcher::set_default(&tracer.dispatch());
+        (tracer, guard)
+    }
+
     pub fn new_with_config(config: TracerConfig) -> Self {
-        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        let counters = TraceCounters::default();
+
+        let (event_tx, event_rx) = event_queue(
+            config.capacity,
+            config.overflow_policy,
+            counters.overflowed.clone(),
+        );
         let (command_tx, command_rx) = mpsc::unbounded_channel();
 
-        let counters = TraceCounters::default();
+        let span_events = config.span_events;
 
         // Create a map of tabs from the config
         let mut tabs = HashMap::new();
@@ -51,21 +78,76 @@ impl Tracer {
             tabs.insert(tab.name, tab.matcher_set);
         }
 
+        // Only callsites the initial tabs could capture are enabled until tabs change
+        let callsite_filter = Arc::new(CallsiteFilter::from_tabs(&tabs));
+
         // Create and start the dispatcher with initial tabs
-        let dispatcher = TracingDispatcher::new(event_rx, command_rx, counters.clone(), tabs);
+        let dispatcher = TracingDispatcher::new(
+            event_rx,
+            command_rx,
+            counters.clone(),
+            tabs,
+            callsite_filter.clone(),
+            config.capacity,
+            config.overflow_policy,
+        );
 
         // Start the dispatcher with a self-consuming run method
+        let dispatcher_state = dispatcher.state_handle();
         tokio::spawn(dispatcher.run());
 
         Self {
             event_tx,
             command_tx,
             counters,
+            span_events,
+            callsite_filter,
+            dispatcher_state,
         }
     }
 
+    /// Create a subscriber feeding this tracer, for installing it yourself (`init`
+    /// installs one globally). It only enables callsites some tab could capture.
+    pub fn subscriber(&self) -> TracingSubscriber {
+        TracingSubscriber::new(self.event_tx.clone(), self.counters.event_id.clone())
+            .with_span_events(self.span_events)
+            .with_callsite_filter(self.callsite_filter.clone())
+    }
+
+    /// Create a dispatch feeding this tracer, for `tracing::dispatcher::with_default`
+    /// or instrumenting futures with `WithSubscriber::with_subscriber`
+    pub fn dispatch(&self) -> Dispatch {
+        Dispatch::new(self.subscriber())
+    }
+
+    /// Create a layer feeding this tracer, for use in a `tracing_subscriber::Registry`
+    /// stack alongside other layers (instead of `init`)
+    #[cfg(feature = "layer")]
+    pub fn layer(&self) -> TracerLayer {
+        TracerLayer::new(self.event_tx.clone(), self.counters.event_id.clone())
+            .with_span_events(self.span_events)
+    }
+
+    /// Create a `log::Log` implementation feeding this tracer, for composing with
+    /// other loggers (instead of `install_log_bridge`)
+    #[cfg(feature = "log")]
+    pub fn log_bridge(&self) -> TracerLogger {
+        TracerLogger::new(self.event_tx.clone(), self.counters.event_id.clone())
+            .with_callsite_filter(self.callsite_filter.clone())
+    }
+
+    /// Route records from the `log` crate through this tracer's tabs. Like `init`,
+    /// this can only succeed once per process.
+    #[cfg(feature = "log")]
+    pub fn install_log_bridge(&self) -> Result<()> {
+        log::set_boxed_logger(Box::new(self.log_bridge()))
+            .context("Failed to set global logger")?;
+        log::set_max_level(log::LevelFilter::Trace);
+        Ok(())
+    }
+
     #[doc(hidden)]
-    pub fn _get_sender_for_testing(&self) -> mpsc::UnboundedSender<TraceEvent> {
+    pub fn _get_sender_for_testing(&self) -> EventSender {
         self.event_tx.clone()
     }
 
@@ -84,6 +166,11 @@ impl Tracer {
         self.counters.get_dropped_count()
     }
 
+    /// Get statistics about events lost because a queue was at capacity
+    pub fn get_overflowed_count(&self) -> u64 {
+        self.counters.get_overflowed_count()
+    }
+
     pub fn set_stdout_callback(&self) -> Result<()> {
         self.set_callback(|event, tab_names| {
             let tab = if tab_names.len() == 1 {
@@ -134,6 +221,60 @@ impl Tracer {
         Ok(response_rx)
     }
 
+    /// Deliver every event emitted so far before the returned receiver resolves
+    pub fn flush(&self) -> Result<oneshot::Receiver<Result<()>>> {
+        let (response_tx, response_rx) = oneshot::channel();
+        let result_sender = ResultSender::new(response_tx);
+
+        self.command_tx
+            .send(DispatcherCommand::Flush(result_sender))
+            .context("Failed to send flush command")?;
+
+        Ok(response_rx)
+    }
+
+    /// Report panics as ERROR records with target `panic`, delivered to the callbacks
+    /// before the previously installed hook (by default, printing the panic) runs.
+    /// The record is routed on the panicking thread rather than queued, so it arrives
+    /// on any runtime, possibly ahead of events still queued. Panics raised by this
+    /// tracer's own callbacks are not reported, since the callback is still running.
+    /// Panic records have no module path, so tabs only capture them through a target,
+    /// file or `all_modules` pattern.
+    pub fn install_panic_hook(&self) {
+        // Neither handle keeps the dispatcher alive once the tracer is dropped
+        let state = self.dispatcher_state.clone();
+        let id_counter = self.counters.event_id.clone();
+        let previous_hook = std::panic::take_hook();
+
+        std::panic::set_hook(Box::new(move |info| {
+            if let Some(state) = state.upgrade() {
+                // Only captured when enabled through RUST_BACKTRACE / RUST_LIB_BACKTRACE
+                let backtrace = Backtrace::capture();
+                let backtrace =
+                    (backtrace.status() == BacktraceStatus::Captured).then_some(&backtrace);
+
+                let event_id = id_counter.fetch_add(1, Ordering::SeqCst);
+                let panic_event = TraceData::from_panic(event_id, info, backtrace);
+
+                // A callback panicking on this thread already holds the state
+                let state = if is_dispatching() {
+                    match state.try_lock() {
+                        Ok(state) => Some(state),
+                        Err(TryLockError::Poisoned(error)) => Some(error.into_inner()),
+                        Err(TryLockError::WouldBlock) => None,
+                    }
+                } else {
+                    Some(lock_state(&state))
+                };
+                if let Some(mut state) = state {
+                    state.handle_event(Arc::new(panic_event));
+                }
+            }
+
+            previous_hook(info);
+        }));
+    }
+
     /// Clear event statistics
     pub fn clear_stats(&self) -> Result<oneshot::Receiver<Result<()>>> {
         let (response_tx, response_rx) = oneshot::channel();
@@ -213,4 +354,23 @@ impl Tracer {
 
         Ok(response_rx)
     }
+
+    /// Report, per tab, whether an event would be captured, silenced or dropped, and
+    /// which matcher decided or which criterion each matcher failed on. The event is
+    /// not dispatched or counted.
+    pub fn explain(
+        &self,
+        event: &TraceData,
+    ) -> Result<oneshot::Receiver<HashMap<String, Explanation>>> {
+        let (response_tx, response_rx) = oneshot::channel();
+
+        self.command_tx
+            .send(DispatcherCommand::Explain(
+                Arc::new(event.clone()),
+                response_tx,
+            ))
+            .context("Failed to send explain command")?;
+
+        Ok(response_rx)
+    }
 }
@@ -1,33 +1,72 @@
 // src/tracer_config.rs
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 
-use crate::{Matcher, MatcherSet};
+use crate::{Matcher, MatcherSet, OverflowPolicy, SpanEventKind, invalid_tab_message};
 
 // Main config structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TracerConfig {
     pub tabs: Vec<TracerTab>,
+    /// Which span lifecycle records the subscriber emits (none by default)
+    #[serde(default)]
+    pub span_events: SpanEvents,
+    /// Maximum number of events waiting for the dispatcher, and waiting for a callback
+    /// to be set (unbounded by default)
+    #[serde(default)]
+    pub capacity: Option<usize>,
+    /// What happens to events arriving while `capacity` is reached
+    #[serde(default)]
+    pub overflow_policy: OverflowPolicy,
 }
 
 impl TracerConfig {
     pub fn empty() -> Self {
-        Self { tabs: vec![] }
-    }
-    pub fn default_main_tab() -> Self {
         Self {
-            tabs: vec![TracerTab::default()],
+            tabs: vec![],
+            span_events: SpanEvents::NONE,
+            capacity: None,
+            overflow_policy: OverflowPolicy::DropNewest,
         }
     }
+    /// Build a config with a single tab, named after the variable, from `RUST_LOG`-style
+    /// directives in an environment variable
+    pub fn from_env(var: &str) -> Result<Self> {
+        let filter = std::env::var(var).with_context(|| format!("Failed to read ${var}"))?;
+        let matcher_set = MatcherSet::from_env_filter(&filter)
+            .with_context(|| format!("Failed to parse ${var}"))?;
+        Ok(Self::empty().with_tab(var, matcher_set))
+    }
+    pub fn default_main_tab() -> Self {
+        Self::from_tab(TracerTab::default())
+    }
     pub fn from_tab(tab: impl Into<TracerTab>) -> Self {
         Self {
             tabs: vec![tab.into()],
+            ..Self::empty()
         }
     }
     pub fn from_tabs<S: Into<TracerTab>>(tabs: impl IntoIterator<Item = S>) -> Self {
         Self {
             tabs: tabs.into_iter().map(Into::into).collect(),
+            ..Self::empty()
         }
     }
+    /// Set which span lifecycle records are emitted and return the modified config
+    pub fn with_span_events(mut self, span_events: SpanEvents) -> Self {
+        self.span_events = span_events;
+        self
+    }
+    /// Bound the event queues and return the modified config
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+    /// Set how events beyond the capacity are handled and return the modified config
+    pub fn with_overflow_policy(mut self, overflow_policy: OverflowPolicy) -> Self {
+        self.overflow_policy = overflow_policy;
+        self
+    }
     /// Add a single tab to the config and return the modified config
     pub fn main_tab(self, matcher_set: impl Into<MatcherSet>) -> Self {
         self.with_tab("Main", matcher_set)
@@ -65,13 +104,90 @@ impl TracerConfig {
         }
     }
 }
-// Configuration struct for tabs
+
+// Span lifecycle records to emit alongside regular events
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpanEvents {
+    pub new: bool,
+    pub enter: bool,
+    pub exit: bool,
+    pub close: bool,
+}
+
+impl SpanEvents {
+    /// No span lifecycle records
+    pub const NONE: Self = Self {
+        new: false,
+        enter: false,
+        exit: false,
+        close: false,
+    };
+    /// Records when spans are created and closed
+    pub const LIFECYCLE: Self = Self {
+        new: true,
+        enter: false,
+        exit: false,
+        close: true,
+    };
+    /// Records every time a span is entered or exited
+    pub const ACTIVE: Self = Self {
+        new: false,
+        enter: true,
+        exit: true,
+        close: false,
+    };
+    /// All span lifecycle records
+    pub const FULL: Self = Self {
+        new: true,
+        enter: true,
+        exit: true,
+        close: true,
+    };
+
+    pub fn is_none(&self) -> bool {
+        *self == Self::NONE
+    }
+
+    pub fn contains(&self, kind: SpanEventKind) -> bool {
+        match kind {
+            SpanEventKind::New => self.new,
+            SpanEventKind::Enter => self.enter,
+            SpanEventKind::Exit => self.exit,
+            SpanEventKind::Close => self.close,
+        }
+    }
+}
+
+// Configuration struct for tabs. Deserializing fails on patterns that do not compile.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "TracerTabFields")]
 pub struct TracerTab {
     pub name: String,
     pub matcher_set: MatcherSet,
 }
 
+#[derive(Deserialize)]
+struct TracerTabFields {
+    name: String,
+    matcher_set: MatcherSet,
+}
+
+impl TryFrom<TracerTabFields> for TracerTab {
+    type Error = String;
+
+    fn try_from(fields: TracerTabFields) -> Result<Self, Self::Error> {
+        fields
+            .matcher_set
+            .validate()
+            .map_err(|errors| invalid_tab_message(&fields.name, &errors))?;
+        Ok(Self {
+            name: fields.name,
+            matcher_set: fields.matcher_set,
+        })
+    }
+}
+
 impl Default for TracerTab {
     fn default() -> Self {
         Self {
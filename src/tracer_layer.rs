@@ -0,0 +1,192 @@
+// src/tracer_layer.rs
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+use tracing::{
+    Event, Subscriber,
+    span::{Attributes, Id, Record},
+};
+use tracing_subscriber::{
+    layer::{Context, Layer},
+    registry::{LookupSpan, SpanRef},
+};
+
+use crate::{
+    EventSender, SpanEventKind, SpanEvents, SpanLifecycle, SpanSnapshot, TraceData, TraceValue,
+    tracing_subscriber::{FieldVisitor, SpanTimings},
+};
+
+// Per-span data kept in the registry's span extensions
+struct LayerSpanData {
+    fields: HashMap<String, TraceValue>,
+    timings: SpanTimings,
+}
+
+// Layer that forwards events to our centralized dispatcher from inside a `Registry` stack
+pub struct TracerLayer {
+    sender: EventSender,
+    id_counter: Arc<AtomicU64>,
+    span_events: SpanEvents,
+}
+
+impl TracerLayer {
+    pub fn new(sender: impl Into<EventSender>, id_counter: Arc<AtomicU64>) -> Self {
+        Self {
+            sender: sender.into(),
+            id_counter,
+            span_events: SpanEvents::NONE,
+        }
+    }
+
+    /// Emit span lifecycle records for the given transitions
+    pub fn with_span_events(mut self, span_events: SpanEvents) -> Self {
+        self.span_events = span_events;
+        self
+    }
+
+    // Send a lifecycle record for a span, built from its registry entry
+    fn emit_span_event<'a, S>(&self, span: &SpanRef<'a, S>, lifecycle: SpanLifecycle)
+    where
+        S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+    {
+        let event_id = self.id_counter.fetch_add(1, Ordering::SeqCst);
+        let mut trace_data = TraceData::from_span(event_id, span.metadata(), lifecycle);
+        if let Some(data) = span.extensions().get::<LayerSpanData>() {
+            trace_data.fields = data.fields.clone();
+        }
+        trace_data.set_spans(span_snapshots(span));
+
+        let _ = self.sender.send(Arc::new(trace_data));
+    }
+
+    // Update the span's timings and emit an enter/exit record when enabled
+    fn transition_span<S>(&self, id: &Id, ctx: &Context<'_, S>, kind: SpanEventKind)
+    where
+        S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+    {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let lifecycle = {
+            let mut extensions = span.extensions_mut();
+            let Some(data) = extensions.get_mut::<LayerSpanData>() else {
+                return;
+            };
+            match kind {
+                SpanEventKind::Enter => {
+                    data.timings.enter();
+                    SpanLifecycle::new(kind)
+                }
+                SpanEventKind::Exit => {
+                    data.timings.exit();
+                    SpanLifecycle::new(kind).with_timings(data.timings.busy, data.timings.idle)
+                }
+                SpanEventKind::New | SpanEventKind::Close => return,
+            }
+        };
+
+        if self.span_events.contains(kind) {
+            self.emit_span_event(&span, lifecycle);
+        }
+    }
+}
+
+// Snapshot a span and its ancestors (root first) from the registry's span scope
+fn span_snapshots<S>(span: &SpanRef<'_, S>) -> Vec<SpanSnapshot>
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    span.scope()
+        .from_root()
+        .map(|span| SpanSnapshot {
+            name: span.name().to_string(),
+            target: span.metadata().target().to_string(),
+            id: span.id().into_u64(),
+            fields: span
+                .extensions()
+                .get::<LayerSpanData>()
+                .map(|data| data.fields.clone())
+                .unwrap_or_default(),
+        })
+        .collect()
+}
+
+impl<S> Layer<S> for TracerLayer
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let mut visitor = FieldVisitor::default();
+        attrs.record(&mut visitor);
+        span.extensions_mut().insert(LayerSpanData {
+            fields: visitor.fields,
+            timings: SpanTimings::new(),
+        });
+
+        if self.span_events.new {
+            self.emit_span_event(&span, SpanLifecycle::new(SpanEventKind::New));
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let mut visitor = FieldVisitor::default();
+        values.record(&mut visitor);
+        if let Some(data) = span.extensions_mut().get_mut::<LayerSpanData>() {
+            // Merge new fields with existing ones
+            data.fields.extend(visitor.fields);
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let event_id = self.id_counter.fetch_add(1, Ordering::SeqCst);
+        let mut trace_data = TraceData::new(event_id, event);
+
+        // The registry resolves explicit parents and the current span for us
+        if let Some(span) = ctx.event_span(event) {
+            trace_data.inherit_span(span.metadata(), span_snapshots(&span));
+        }
+
+        let _ = self.sender.send(Arc::new(trace_data));
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        self.transition_span(id, &ctx, SpanEventKind::Enter);
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        self.transition_span(id, &ctx, SpanEventKind::Exit);
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        if !self.span_events.close {
+            return;
+        }
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+
+        let lifecycle = {
+            let mut extensions = span.extensions_mut();
+            let Some(data) = extensions.get_mut::<LayerSpanData>() else {
+                return;
+            };
+            data.timings.close();
+            SpanLifecycle::new(SpanEventKind::Close)
+                .with_timings(data.timings.busy, data.timings.idle)
+        };
+        self.emit_span_event(&span, lifecycle);
+    }
+}
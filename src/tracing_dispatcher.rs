@@ -2,15 +2,19 @@
 
 use anyhow::{Result, anyhow};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     sync::{
-        Arc,
+        Arc, Mutex, MutexGuard, PoisonError, Weak,
         atomic::{AtomicU64, Ordering},
     },
 };
 use tokio::sync::{mpsc, oneshot};
 
-use crate::{DroppedEventCallback, EventCallback, MatcherSet, SilencedEventCallback, TraceEvent};
+use crate::{
+    CallsiteFilter, DispatchingGuard, DroppedEventCallback, EventCallback, EventReceiver,
+    Explanation, MatcherSet, OverflowPolicy, PatternError, SilencedEventCallback, TraceEvent,
+    make_room,
+};
 
 pub(crate) enum DispatcherCommand {
     SetCallback(EventCallback, ResultSender),
@@ -20,44 +24,75 @@ pub(crate) enum DispatcherCommand {
     UpdateTab(String, MatcherSet, ResultSender),
     RemoveTab(String, ResultSender),
     ClearStats(ResultSender),
+    Flush(ResultSender),
+    Explain(TraceEvent, oneshot::Sender<HashMap<String, Explanation>>),
 }
 
 pub(crate) struct TracingDispatcher {
-    event_rx: mpsc::UnboundedReceiver<TraceEvent>,
+    event_rx: EventReceiver,
     command_rx: mpsc::UnboundedReceiver<DispatcherCommand>,
+    state: Arc<Mutex<DispatcherState>>,
+}
+
+// Tabs and callbacks, shared so events can also be routed outside the run loop (the
+// panic hook can't wait for it). Only the dispatcher holds it strongly, so it goes
+// away with the dispatcher.
+pub(crate) struct DispatcherState {
     counters: TraceCounters,
     tabs: HashMap<String, MatcherSet>,
     callback: Option<EventCallback>,
     silenced_callback: Option<SilencedEventCallback>,
     dropped_callback: Option<DroppedEventCallback>,
-    pending_captured_events: Vec<(TraceEvent, Vec<String>)>,
+    pending_captured_events: VecDeque<(TraceEvent, Vec<String>)>,
+    pending_capacity: Option<usize>,
+    overflow_policy: OverflowPolicy,
+    callsite_filter: Arc<CallsiteFilter>,
 }
 
 impl TracingDispatcher {
     pub fn new(
-        event_rx: mpsc::UnboundedReceiver<TraceEvent>,
+        event_rx: EventReceiver,
         command_rx: mpsc::UnboundedReceiver<DispatcherCommand>,
         counters: TraceCounters,
         initial_tabs: HashMap<String, MatcherSet>,
+        callsite_filter: Arc<CallsiteFilter>,
+        pending_capacity: Option<usize>,
+        overflow_policy: OverflowPolicy,
     ) -> Self {
-        Self {
-            event_rx,
-            command_rx,
+        let state = DispatcherState {
             counters,
             tabs: initial_tabs,
             callback: None,
             silenced_callback: None,
             dropped_callback: None,
-            pending_captured_events: Vec::new(),
+            pending_captured_events: VecDeque::new(),
+            pending_capacity,
+            overflow_policy,
+            callsite_filter,
+        };
+        Self {
+            event_rx,
+            command_rx,
+            state: Arc::new(Mutex::new(state)),
         }
     }
 
+    /// Handle to the routing state that doesn't keep the dispatcher alive
+    pub fn state_handle(&self) -> Weak<Mutex<DispatcherState>> {
+        Arc::downgrade(&self.state)
+    }
+
+    // A callback that panicked poisons the lock, but the state stays consistent
+    fn state(&self) -> MutexGuard<'_, DispatcherState> {
+        lock_state(&self.state)
+    }
+
     // Main run loop that consumes self
     pub async fn run(mut self) {
         loop {
             tokio::select! {
                 Some(event) = self.event_rx.recv() => {
-                    self.handle_event(event);
+                    self.state().handle_event(event);
                 },
 
                 Some(cmd) = self.command_rx.recv() => {
@@ -69,31 +104,76 @@ impl TracingDispatcher {
         }
     }
 
-    fn handle_event(&mut self, event: TraceEvent) {
+    // Handle a dispatcher command
+    async fn handle_command(&mut self, cmd: DispatcherCommand) {
+        match cmd {
+            DispatcherCommand::SetCallback(cb, response_tx) => {
+                self.state().handle_set_callback(cb, response_tx);
+            }
+            DispatcherCommand::SetSilencedCallback(cb, response_tx) => {
+                self.state().handle_set_silenced_callback(cb, response_tx);
+            }
+            DispatcherCommand::SetUncapturedCallback(cb, response_tx) => {
+                self.state().handle_set_dropped_callback(cb, response_tx);
+            }
+            DispatcherCommand::AddTab(name, filter_set, response_tx) => {
+                self.state().handle_add_tab(name, filter_set, response_tx);
+            }
+            DispatcherCommand::UpdateTab(name, filter_set, response_tx) => {
+                self.state()
+                    .handle_update_tab(name, filter_set, response_tx);
+            }
+            DispatcherCommand::RemoveTab(name, response_tx) => {
+                self.state().handle_remove_tab(name, response_tx);
+            }
+            DispatcherCommand::ClearStats(response_tx) => {
+                self.state().handle_clear_stats(response_tx);
+            }
+            DispatcherCommand::Flush(response_tx) => {
+                self.handle_flush(response_tx);
+            }
+            DispatcherCommand::Explain(event, response_tx) => {
+                self.state().handle_explain(event, response_tx);
+            }
+        }
+    }
+
+    // Process everything queued before the flush was requested
+    fn handle_flush(&mut self, response_tx: ResultSender) {
+        while let Some(event) = self.event_rx.try_recv() {
+            lock_state(&self.state).handle_event(event);
+        }
+        response_tx.success();
+    }
+}
+
+pub(crate) fn lock_state(state: &Mutex<DispatcherState>) -> MutexGuard<'_, DispatcherState> {
+    state.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl DispatcherState {
+    // Let the subscriber know which callsites can still reach us
+    fn rebuild_callsite_filter(&self) {
+        self.callsite_filter.rebuild(
+            &self.tabs,
+            self.silenced_callback.is_some(),
+            self.dropped_callback.is_some(),
+        );
+    }
+
+    /// Judge an event against every tab and hand it to the matching callback
+    pub fn handle_event(&mut self, event: TraceEvent) {
+        // Callbacks that log must not wait on a queue only this thread drains
+        let _dispatching = DispatchingGuard::enter();
         let mut captured_by = Vec::new();
         let mut silenced_by = Vec::new();
 
-        // Check each tab
+        // Each tab's set decides by its own match order
         for (name, filter) in &self.tabs {
-            // First check if any exclusion filters apply - these take precedence
-            let mut is_silenced = false;
-
-            for matcher in filter.iter_matchers() {
-                if !matcher.include && matcher.matches(&event) {
-                    silenced_by.push(name.clone());
-                    is_silenced = true;
-                    break;
-                }
-            }
-
-            // If not silenced, check if any inclusion filters match
-            if !is_silenced {
-                for matcher in filter.iter_matchers() {
-                    if matcher.include && matcher.matches(&event) {
-                        captured_by.push(name.clone());
-                        break;
-                    }
-                }
+            match filter.evaluate(&event) {
+                Some(matcher) if matcher.include => captured_by.push(name.clone()),
+                Some(_) => silenced_by.push(name.clone()),
+                None => {}
             }
         }
 
@@ -106,8 +186,7 @@ impl TracingDispatcher {
                 cb(Arc::clone(&event), &tab_refs);
             } else {
                 // Store event for later processing when callback is set
-                self.pending_captured_events
-                    .push((Arc::clone(&event), captured_by));
+                self.push_pending(Arc::clone(&event), captured_by);
             }
         } else if !silenced_by.is_empty() {
             self.counters.silenced.fetch_add(1, Ordering::SeqCst);
@@ -124,11 +203,32 @@ impl TracingDispatcher {
         }
     }
 
+    // Buffer a captured event until a callback is set, within the configured capacity.
+    // The dispatcher can't block, so `Block` drops the newest event here.
+    fn push_pending(&mut self, event: TraceEvent, tabs: Vec<String>) {
+        if let Some(capacity) = self.pending_capacity
+            && self.pending_captured_events.len() >= capacity
+        {
+            self.counters.overflowed.fetch_add(1, Ordering::SeqCst);
+            let keep = make_room(
+                &mut self.pending_captured_events,
+                event.level.0,
+                |(event, _)| event.level.0,
+                self.overflow_policy,
+            );
+            if !keep {
+                return;
+            }
+        }
+        self.pending_captured_events.push_back((event, tabs));
+    }
+
     fn handle_set_callback(&mut self, cb: EventCallback, response_tx: ResultSender) {
         // Set the new callback
         self.callback = Some(cb);
 
         // Drain pending captured events
+        let _dispatching = DispatchingGuard::enter();
         if let Some(callback) = &self.callback {
             for (event, tabs) in self.pending_captured_events.drain(..) {
                 // Convert to references for the callback
@@ -146,42 +246,17 @@ impl TracingDispatcher {
     ) {
         // Set the new callback
         self.silenced_callback = Some(cb);
+        self.rebuild_callsite_filter();
         response_tx.success();
     }
 
     fn handle_set_dropped_callback(&mut self, cb: DroppedEventCallback, response_tx: ResultSender) {
         // Set the new callback
         self.dropped_callback = Some(cb);
+        self.rebuild_callsite_filter();
         response_tx.success();
     }
 
-    // Handle a dispatcher command
-    async fn handle_command(&mut self, cmd: DispatcherCommand) {
-        match cmd {
-            DispatcherCommand::SetCallback(cb, response_tx) => {
-                self.handle_set_callback(cb, response_tx);
-            }
-            DispatcherCommand::SetSilencedCallback(cb, response_tx) => {
-                self.handle_set_silenced_callback(cb, response_tx);
-            }
-            DispatcherCommand::SetUncapturedCallback(cb, response_tx) => {
-                self.handle_set_dropped_callback(cb, response_tx);
-            }
-            DispatcherCommand::AddTab(name, filter_set, response_tx) => {
-                self.handle_add_tab(name, filter_set, response_tx);
-            }
-            DispatcherCommand::UpdateTab(name, filter_set, response_tx) => {
-                self.handle_update_tab(name, filter_set, response_tx);
-            }
-            DispatcherCommand::RemoveTab(name, response_tx) => {
-                self.handle_remove_tab(name, response_tx);
-            }
-            DispatcherCommand::ClearStats(response_tx) => {
-                self.handle_clear_stats(response_tx);
-            }
-        }
-    }
-
     // Simplified add_tab handler
     fn handle_add_tab(
         &mut self,
@@ -189,8 +264,15 @@ impl TracingDispatcher {
         filter_set: MatcherSet,
         response_tx: ResultSender,
     ) {
+        let name = name.into();
+        if let Err(errors) = filter_set.validate() {
+            response_tx.error(invalid_tab_message(&name, &errors));
+            return;
+        }
+
         // Add the tab to the map
-        self.tabs.insert(name.into(), filter_set);
+        self.tabs.insert(name, filter_set);
+        self.rebuild_callsite_filter();
         response_tx.success();
     }
 
@@ -206,9 +288,14 @@ impl TracingDispatcher {
             response_tx.error(format!("Subscriber '{name:?}' not found"));
             return;
         }
+        if let Err(errors) = filter_set.validate() {
+            response_tx.error(invalid_tab_message(name, &errors));
+            return;
+        }
 
         // Update the filter set
         self.tabs.insert(name.to_string(), filter_set);
+        self.rebuild_callsite_filter();
         response_tx.success();
     }
 
@@ -222,9 +309,24 @@ impl TracingDispatcher {
 
         // Remove the tab
         self.tabs.remove(name);
+        self.rebuild_callsite_filter();
         response_tx.success();
     }
 
+    // Judge an event against every tab without dispatching it
+    fn handle_explain(
+        &self,
+        event: TraceEvent,
+        response_tx: oneshot::Sender<HashMap<String, Explanation>>,
+    ) {
+        let explanations = self
+            .tabs
+            .iter()
+            .map(|(name, filter)| (name.clone(), filter.explain(&event)))
+            .collect();
+        let _ = response_tx.send(explanations);
+    }
+
     // Simplified clear_stats handler
     fn handle_clear_stats(&mut self, response_tx: ResultSender) {
         // Reset statistics
@@ -233,6 +335,12 @@ impl TracingDispatcher {
     }
 }
 
+// Name the tab along with every pattern that kept its set from compiling
+pub(crate) fn invalid_tab_message(name: &str, errors: &[PatternError]) -> String {
+    let errors: Vec<_> = errors.iter().map(ToString::to_string).collect();
+    format!("Tab '{name}' has {}", errors.join(", "))
+}
+
 // Result sender for operation responses
 pub struct ResultSender(oneshot::Sender<Result<()>>);
 
@@ -257,6 +365,7 @@ pub(crate) struct TraceCounters {
     pub captured: Arc<AtomicU64>,
     pub silenced: Arc<AtomicU64>,
     pub dropped: Arc<AtomicU64>,
+    pub overflowed: Arc<AtomicU64>,
 }
 
 impl TraceCounters {
@@ -264,6 +373,7 @@ impl TraceCounters {
         self.captured.store(0, Ordering::SeqCst);
         self.silenced.store(0, Ordering::SeqCst);
         self.dropped.store(0, Ordering::SeqCst);
+        self.overflowed.store(0, Ordering::SeqCst);
     }
 
     // Return captured count
@@ -280,4 +390,9 @@ impl TraceCounters {
     pub fn get_dropped_count(&self) -> u64 {
         self.dropped.load(Ordering::SeqCst)
     }
+
+    // Get count of events lost to a full queue
+    pub fn get_overflowed_count(&self) -> u64 {
+        self.overflowed.load(Ordering::SeqCst)
+    }
 }
@@ -1,79 +1,332 @@
 // src/tracing_subscriber.rs
 use std::{
     collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
     sync::{
         Arc, Mutex,
         atomic::{AtomicU64, Ordering},
     },
+    time::{Duration, Instant},
 };
-use tokio::sync::mpsc;
 use tracing::{
-    Event, Metadata, Subscriber,
+    Dispatch, Event, Metadata, Subscriber,
     field::{Field, Visit},
     span::{Attributes, Id, Record},
 };
 use tracing_core::subscriber::Interest;
 
-use crate::{TraceData, TraceEvent};
+use crate::{
+    CallsiteFilter, EventSender, SpanEventKind, SpanEvents, SpanLifecycle, SpanSnapshot, TraceData,
+    TraceValue,
+};
 
 // Complete span information storage - we need all of this for trace events
 #[derive(Debug, Clone)]
 struct SpanInfo {
     name: String,
-    target: String,
-    module_path: Option<String>,
-    file: Option<String>,
-    line: Option<u32>,
     parent_id: Option<u64>,
-    fields: HashMap<String, String>,
+    fields: HashMap<String, TraceValue>,
     metadata: &'static Metadata<'static>,
+    timings: SpanTimings,
+    // Live handles to this span, plus one for each child span still open
+    ref_count: usize,
 }
 
-// Thread-local span stack for tracking current span context
-thread_local! {
-    static SPAN_STACK: std::cell::RefCell<Vec<u64>> = const { std::cell::RefCell::new(Vec::new()) };
+// Busy/idle bookkeeping for span lifecycle records
+#[derive(Debug, Clone)]
+pub(crate) struct SpanTimings {
+    pub(crate) busy: Duration,
+    pub(crate) idle: Duration,
+    last_transition: Instant,
+    active_entries: usize,
+}
+
+impl SpanTimings {
+    pub(crate) fn new() -> Self {
+        Self {
+            busy: Duration::ZERO,
+            idle: Duration::ZERO,
+            last_transition: Instant::now(),
+            active_entries: 0,
+        }
+    }
+
+    pub(crate) fn enter(&mut self) {
+        if self.active_entries == 0 {
+            let now = Instant::now();
+            self.idle += now - self.last_transition;
+            self.last_transition = now;
+        }
+        self.active_entries += 1;
+    }
+
+    pub(crate) fn exit(&mut self) {
+        self.active_entries = self.active_entries.saturating_sub(1);
+        if self.active_entries == 0 {
+            let now = Instant::now();
+            self.busy += now - self.last_transition;
+            self.last_transition = now;
+        }
+    }
+
+    pub(crate) fn close(&mut self) {
+        let now = Instant::now();
+        if self.active_entries == 0 {
+            self.idle += now - self.last_transition;
+        } else {
+            self.busy += now - self.last_transition;
+        }
+        self.last_transition = now;
+    }
+}
+
+// Execution context a span is entered from: the tokio task when inside a runtime,
+// otherwise the OS thread. Keying on the task keeps a task's span stack intact when
+// it is resumed on a different worker thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ContextKey {
+    Task(tokio::task::Id),
+    Thread(std::thread::ThreadId),
+}
+
+impl ContextKey {
+    fn current() -> Self {
+        match tokio::task::try_id() {
+            Some(task_id) => ContextKey::Task(task_id),
+            None => ContextKey::Thread(std::thread::current().id()),
+        }
+    }
+}
+
+// Shards of `SpanContexts`, so contexts on different threads rarely share a lock
+const CONTEXT_SHARDS: usize = 64;
+
+type ContextStacks = HashMap<ContextKey, Vec<u64>>;
+
+// Per-context stacks of entered spans, tracked through enter/exit pairs. Every event,
+// enter and exit looks up its own context, so the stacks are sharded by context.
+#[derive(Debug)]
+struct SpanContexts {
+    shards: Box<[Mutex<ContextStacks>]>,
+}
+
+impl Default for SpanContexts {
+    fn default() -> Self {
+        Self {
+            shards: (0..CONTEXT_SHARDS).map(|_| Mutex::default()).collect(),
+        }
+    }
+}
+
+impl SpanContexts {
+    fn shard(&self, key: &ContextKey) -> &Mutex<ContextStacks> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % self.shards.len()]
+    }
+
+    fn current(&self) -> Option<u64> {
+        let key = ContextKey::current();
+        let stacks = self.shard(&key).lock().unwrap();
+        stacks.get(&key).and_then(|stack| stack.last().copied())
+    }
+
+    fn enter(&self, span_id: u64) {
+        let key = ContextKey::current();
+        let mut stacks = self.shard(&key).lock().unwrap();
+        stacks.entry(key).or_default().push(span_id);
+    }
+
+    fn exit(&self, span_id: u64) {
+        // The span should be the last one entered from this context
+        let key = ContextKey::current();
+        {
+            let mut stacks = self.shard(&key).lock().unwrap();
+            if let Some(stack) = stacks.get_mut(&key)
+                && remove_entry(stack, span_id)
+            {
+                // Drop empty stacks so finished tasks and threads don't accumulate
+                if stack.is_empty() {
+                    stacks.remove(&key);
+                }
+                return;
+            }
+        }
+
+        // Otherwise the guard moved between contexts. Its entry can only be told apart
+        // when a single context holds the span, since several tasks may enter it at once.
+        // Shards are locked in order, so concurrent fallbacks can't deadlock.
+        let mut shards: Vec<_> = self
+            .shards
+            .iter()
+            .map(|shard| shard.lock().unwrap())
+            .collect();
+        let holders: Vec<_> = shards
+            .iter()
+            .enumerate()
+            .flat_map(|(index, stacks)| {
+                stacks
+                    .iter()
+                    .filter(|(_, stack)| stack.contains(&span_id))
+                    .map(move |(key, _)| (index, *key))
+            })
+            .collect();
+        if let [(index, holder)] = holders[..] {
+            let stacks = &mut shards[index];
+            if let Some(stack) = stacks.get_mut(&holder) {
+                remove_entry(stack, span_id);
+                if stack.is_empty() {
+                    stacks.remove(&holder);
+                }
+            }
+        }
+    }
+}
+
+// Remove the innermost entry of a span from a stack, if it is there
+fn remove_entry(stack: &mut Vec<u64>, span_id: u64) -> bool {
+    match stack.iter().rposition(|&id| id == span_id) {
+        Some(pos) => {
+            stack.remove(pos);
+            true
+        }
+        None => false,
+    }
 }
 
 // Custom subscriber that forwards events to our centralized dispatcher
 pub struct TracingSubscriber {
-    sender: mpsc::UnboundedSender<TraceEvent>,
+    sender: EventSender,
     id_counter: Arc<AtomicU64>,
     span_storage: Arc<Mutex<HashMap<u64, SpanInfo>>>,
+    span_events: SpanEvents,
+    span_contexts: Arc<SpanContexts>,
+    callsite_filter: Arc<CallsiteFilter>,
 }
 
 impl TracingSubscriber {
-    pub fn new(sender: mpsc::UnboundedSender<TraceEvent>, id_counter: Arc<AtomicU64>) -> Self {
+    pub fn new(sender: impl Into<EventSender>, id_counter: Arc<AtomicU64>) -> Self {
         Self {
-            sender,
+            sender: sender.into(),
             id_counter,
             span_storage: Arc::new(Mutex::new(HashMap::new())),
+            span_events: SpanEvents::NONE,
+            span_contexts: Arc::new(SpanContexts::default()),
+            callsite_filter: Arc::new(CallsiteFilter::accept_all()),
         }
     }
 
-    // Get current span ID from thread-local stack
+    /// Emit span lifecycle records for the given transitions
+    pub fn with_span_events(mut self, span_events: SpanEvents) -> Self {
+        self.span_events = span_events;
+        self
+    }
+
+    // Only produce events some tab of the owning tracer could capture
+    pub(crate) fn with_callsite_filter(mut self, callsite_filter: Arc<CallsiteFilter>) -> Self {
+        self.callsite_filter = callsite_filter;
+        self
+    }
+
+    // Get current span ID for the running task (or thread outside a runtime)
     fn current_span_id(&self) -> Option<u64> {
-        SPAN_STACK.with(|stack| stack.borrow().last().copied())
+        self.span_contexts.current()
     }
 
-    // Build complete span hierarchy traversing parent relationships
-    fn build_span_hierarchy(&self, span_id: u64) -> String {
+    // Snapshot a span and its ancestors, traversing parent relationships
+    fn build_span_snapshots(&self, span_id: u64) -> Vec<SpanSnapshot> {
         let storage = self.span_storage.lock().unwrap();
-        let mut hierarchy = Vec::new();
-        let mut current_id = Some(span_id);
-
-        // Traverse up the parent chain
-        while let Some(id) = current_id {
-            if let Some(span_info) = storage.get(&id) {
-                hierarchy.push(span_info.name.clone());
-                current_id = span_info.parent_id;
-            } else {
+        span_snapshots(&storage, span_id)
+    }
+
+    // Send a lifecycle record for a span through the dispatcher channel
+    fn emit_span_event(
+        &self,
+        span_info: &SpanInfo,
+        spans: Vec<SpanSnapshot>,
+        lifecycle: SpanLifecycle,
+    ) {
+        let event_id = self.id_counter.fetch_add(1, Ordering::SeqCst);
+        let mut trace_data = TraceData::from_span(event_id, span_info.metadata, lifecycle);
+        trace_data.fields = span_info.fields.clone();
+        trace_data.set_spans(spans);
+
+        let _ = self.sender.send(Arc::new(trace_data));
+    }
+
+    // Update the span's timings and emit an enter/exit record when enabled
+    fn transition_span(&self, span_id: u64, kind: SpanEventKind) {
+        let Ok(mut storage) = self.span_storage.lock() else {
+            return;
+        };
+        let Some(span_info) = storage.get_mut(&span_id) else {
+            return;
+        };
+
+        let lifecycle = match kind {
+            SpanEventKind::Enter => {
+                span_info.timings.enter();
+                SpanLifecycle::new(kind)
+            }
+            SpanEventKind::Exit => {
+                span_info.timings.exit();
+                SpanLifecycle::new(kind)
+                    .with_timings(span_info.timings.busy, span_info.timings.idle)
+            }
+            SpanEventKind::New | SpanEventKind::Close => return,
+        };
+
+        if self.span_events.contains(kind) {
+            let span_info = span_info.clone();
+            let spans = span_snapshots(&storage, span_id);
+            drop(storage);
+            self.emit_span_event(&span_info, spans, lifecycle);
+        }
+    }
+
+    // Release one reference to a span. When the last reference is gone the span is
+    // removed from storage (emitting its close record when enabled) and releases the
+    // reference it held on its parent. Returns whether the span itself was closed.
+    fn release_span(&self, span_id: u64) -> bool {
+        let mut next_id = Some(span_id);
+        let mut closed = false;
+
+        while let Some(current_id) = next_id.take() {
+            let Ok(mut storage) = self.span_storage.lock() else {
+                break;
+            };
+            let Some(span_info) = storage.get_mut(&current_id) else {
+                break;
+            };
+
+            span_info.ref_count = span_info.ref_count.saturating_sub(1);
+            if span_info.ref_count > 0 {
                 break;
             }
+
+            let spans = span_snapshots(&storage, current_id);
+            let Some(mut span_info) = storage.remove(&current_id) else {
+                break;
+            };
+            drop(storage);
+
+            if self.span_events.close {
+                span_info.timings.close();
+                let lifecycle = SpanLifecycle::new(SpanEventKind::Close)
+                    .with_timings(span_info.timings.busy, span_info.timings.idle);
+                self.emit_span_event(&span_info, spans, lifecycle);
+            }
+
+            closed |= current_id == span_id;
+            next_id = span_info.parent_id;
         }
 
-        // Reverse to get root-to-leaf order
-        hierarchy.reverse();
-        hierarchy.join("::")
+        closed
+    }
+
+    #[doc(hidden)]
+    pub fn _live_span_count_for_testing(&self) -> usize {
+        self.span_storage.lock().unwrap().len()
     }
 
     // Get span info by ID - we need all the span info for the trace event
@@ -83,24 +336,49 @@ impl TracingSubscriber {
     }
 
     // Create complete field visitor for extracting all field data
-    fn extract_fields(&self, attributes: &Attributes<'_>) -> HashMap<String, String> {
+    fn extract_fields(&self, attributes: &Attributes<'_>) -> HashMap<String, TraceValue> {
         let mut visitor = FieldVisitor::default();
         attributes.record(&mut visitor);
         visitor.fields
     }
 
     // Extract fields from a Record
-    fn extract_record_fields(&self, record: &Record<'_>) -> HashMap<String, String> {
+    fn extract_record_fields(&self, record: &Record<'_>) -> HashMap<String, TraceValue> {
         let mut visitor = FieldVisitor::default();
         record.record(&mut visitor);
         visitor.fields
     }
 }
 
+// Snapshot a span and its ancestors (root first) from locked storage
+fn span_snapshots(storage: &HashMap<u64, SpanInfo>, span_id: u64) -> Vec<SpanSnapshot> {
+    let mut spans = Vec::new();
+    let mut current_id = Some(span_id);
+
+    // Traverse up the parent chain
+    while let Some(id) = current_id {
+        if let Some(span_info) = storage.get(&id) {
+            spans.push(SpanSnapshot {
+                name: span_info.name.clone(),
+                target: span_info.metadata.target().to_string(),
+                id,
+                fields: span_info.fields.clone(),
+            });
+            current_id = span_info.parent_id;
+        } else {
+            break;
+        }
+    }
+
+    // Reverse to get root-to-leaf order
+    spans.reverse();
+    spans
+}
+
 // Complete field visitor implementation
 #[derive(Default)]
-struct FieldVisitor {
-    fields: HashMap<String, String>,
+pub(crate) struct FieldVisitor {
+    pub(crate) fields: HashMap<String, TraceValue>,
     message: Option<String>,
 }
 
@@ -109,8 +387,10 @@ impl Visit for FieldVisitor {
         if field.name() == "message" {
             self.message = Some(format!("{value:?}"));
         } else {
-            self.fields
-                .insert(field.name().to_string(), format!("{value:?}"));
+            self.fields.insert(
+                field.name().to_string(),
+                TraceValue::Debug(format!("{value:?}")),
+            );
         }
     }
 
@@ -119,63 +399,99 @@ impl Visit for FieldVisitor {
             self.message = Some(value.to_string());
         } else {
             self.fields
-                .insert(field.name().to_string(), value.to_string());
+                .insert(field.name().to_string(), TraceValue::from(value));
         }
     }
 
     fn record_i64(&mut self, field: &Field, value: i64) {
         self.fields
-            .insert(field.name().to_string(), value.to_string());
+            .insert(field.name().to_string(), TraceValue::I64(value));
     }
 
     fn record_u64(&mut self, field: &Field, value: u64) {
         self.fields
-            .insert(field.name().to_string(), value.to_string());
+            .insert(field.name().to_string(), TraceValue::U64(value));
     }
 
     fn record_bool(&mut self, field: &Field, value: bool) {
         self.fields
-            .insert(field.name().to_string(), value.to_string());
+            .insert(field.name().to_string(), TraceValue::Bool(value));
     }
 
     fn record_f64(&mut self, field: &Field, value: f64) {
         self.fields
-            .insert(field.name().to_string(), value.to_string());
+            .insert(field.name().to_string(), TraceValue::F64(value));
+    }
+
+    fn record_error(&mut self, field: &Field, value: &(dyn std::error::Error + 'static)) {
+        self.fields
+            .insert(field.name().to_string(), TraceValue::from_error(value));
+    }
+
+    #[cfg(tracing_unstable)]
+    fn record_value(&mut self, field: &Field, value: valuable::Value<'_>) {
+        self.fields
+            .insert(field.name().to_string(), TraceValue::from_valuable(value));
     }
 }
 
 impl Subscriber for TracingSubscriber {
-    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
-        // Always enable - let our dispatcher handle filtering
-        true
+    fn on_register_dispatch(&self, dispatch: &Dispatch) {
+        self.callsite_filter.register_dispatch(dispatch);
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        // Skip callsites no tab could capture; the dispatcher does the precise filtering
+        self.callsite_filter.enabled(metadata)
     }
 
     fn new_span(&self, span: &Attributes<'_>) -> Id {
-        let span_id_u64 = self.id_counter.fetch_add(1, Ordering::SeqCst);
+        // Span ids must be non-zero, while the shared counter starts at zero
+        let span_id_u64 = self.id_counter.fetch_add(1, Ordering::SeqCst) + 1;
         let span_id = Id::from_u64(span_id_u64);
         let metadata = span.metadata();
 
-        // Get parent span ID from current context
-        let parent_id = self.current_span_id();
+        // Get parent span ID, honoring explicit parents before the current context
+        let parent_id = if span.is_root() {
+            None
+        } else if let Some(parent) = span.parent() {
+            Some(parent.into_u64())
+        } else {
+            self.current_span_id()
+        };
 
         // Extract all fields from the span attributes
         let fields = self.extract_fields(span);
 
+        let mut storage = self.span_storage.lock().unwrap();
+
+        // The child holds a reference on its parent so the hierarchy outlives the
+        // parent's own handles
+        let parent_id = parent_id.filter(|parent_id| match storage.get_mut(parent_id) {
+            Some(parent) => {
+                parent.ref_count += 1;
+                true
+            }
+            None => false,
+        });
+
         // Create complete span info - we need all of this for trace events
         let span_info = SpanInfo {
             name: metadata.name().to_string(),
-            target: metadata.target().to_string(),
-            module_path: metadata.module_path().map(|s| s.to_string()),
-            file: metadata.file().map(|s| s.to_string()),
-            line: metadata.line(),
             parent_id,
             fields,
             metadata,
+            timings: SpanTimings::new(),
+            ref_count: 1,
         };
 
         // Store span info for hierarchy tracking
-        {
-            let mut storage = self.span_storage.lock().unwrap();
+        if self.span_events.new {
+            storage.insert(span_id_u64, span_info.clone());
+            let spans = span_snapshots(&storage, span_id_u64);
+            drop(storage);
+            self.emit_span_event(&span_info, spans, SpanLifecycle::new(SpanEventKind::New));
+        } else {
             storage.insert(span_id_u64, span_info);
         }
 
@@ -187,11 +503,11 @@ impl Subscriber for TracingSubscriber {
         let span_id_u64 = span.into_u64();
         let new_fields = self.extract_record_fields(values);
 
-        if let Ok(mut storage) = self.span_storage.lock() {
-            if let Some(span_info) = storage.get_mut(&span_id_u64) {
-                // Merge new fields with existing ones
-                span_info.fields.extend(new_fields);
-            }
+        if let Ok(mut storage) = self.span_storage.lock()
+            && let Some(span_info) = storage.get_mut(&span_id_u64)
+        {
+            // Merge new fields with existing ones
+            span_info.fields.extend(new_fields);
         }
     }
 
@@ -209,33 +525,18 @@ impl Subscriber for TracingSubscriber {
         let mut trace_data = TraceData::new(event_id, event);
 
         // Get current span context and enrich the trace event with span information
-        if let Some(current_span_id) = self.current_span_id() {
-            if let Some(span_info) = self.get_span_info(current_span_id) {
-                // Set span-specific information
-                trace_data.span_name = Some(span_info.name.clone());
-                trace_data.span_hierarchy = Some(self.build_span_hierarchy(current_span_id));
-
-                // If the event doesn't have its own module/file/line info, inherit from span
-                if trace_data.module_path.is_none() && span_info.module_path.is_some() {
-                    trace_data.module_path = span_info.module_path;
-                }
-                if trace_data.file.is_none() && span_info.file.is_some() {
-                    trace_data.file = span_info.file;
-                }
-                if trace_data.line.is_none() && span_info.line.is_some() {
-                    trace_data.line = span_info.line;
-                }
-
-                // Merge span fields with event fields (event fields take precedence)
-                let mut combined_fields = span_info.fields;
-                combined_fields.extend(trace_data.fields.clone());
-                trace_data.fields = combined_fields;
-
-                // Update target if the event target is generic but span has specific target
-                if trace_data.target.is_empty() || trace_data.target == "unknown" {
-                    trace_data.target = span_info.target;
-                }
-            }
+        let current_span_id = if event.is_root() {
+            None
+        } else if let Some(parent) = event.parent() {
+            Some(parent.into_u64())
+        } else {
+            self.current_span_id()
+        };
+        if let Some(current_span_id) = current_span_id
+            && let Some(span_info) = self.get_span_info(current_span_id)
+        {
+            let spans = self.build_span_snapshots(current_span_id);
+            trace_data.inherit_span(span_info.metadata, spans);
         }
 
         // Wrap in Arc for sharing
@@ -246,67 +547,61 @@ impl Subscriber for TracingSubscriber {
     }
 
     fn enter(&self, span: &Id) {
-        // Push span onto thread-local stack when entered
+        // Push span onto the current context's stack when entered
         let span_id_u64 = span.into_u64();
-        SPAN_STACK.with(|stack| {
-            stack.borrow_mut().push(span_id_u64);
-        });
+        self.span_contexts.enter(span_id_u64);
+        self.transition_span(span_id_u64, SpanEventKind::Enter);
     }
 
     fn exit(&self, span: &Id) {
-        // Pop span from thread-local stack when exited
+        // Pop span from the stack of the context that entered it
         let span_id_u64 = span.into_u64();
-        SPAN_STACK.with(|stack| {
-            let mut stack = stack.borrow_mut();
-            // Find and remove the span (should be the last one)
-            if let Some(pos) = stack.iter().rposition(|&id| id == span_id_u64) {
-                stack.remove(pos);
-            }
-        });
+        self.span_contexts.exit(span_id_u64);
+        self.transition_span(span_id_u64, SpanEventKind::Exit);
     }
 
     fn clone_span(&self, id: &Id) -> Id {
-        // Return the same ID - spans are reference counted internally by tracing
+        // Count the new handle so the span outlives the one it was cloned from
+        if let Ok(mut storage) = self.span_storage.lock()
+            && let Some(span_info) = storage.get_mut(&id.into_u64())
+        {
+            span_info.ref_count += 1;
+        }
         id.clone()
     }
 
     fn drop_span(&self, id: Id) {
-        // Clean up span data when it's dropped
-        let span_id_u64 = id.into_u64();
-        if let Ok(mut storage) = self.span_storage.lock() {
-            storage.remove(&span_id_u64);
-        }
+        // Deprecated in favor of try_close, kept for callers that still use it
+        let _ = self.try_close(id);
     }
 
-    fn try_close(&self, _id: Id) -> bool {
-        // Indicate that we can close the span
-        // Clean up happens in drop_span
-        true
+    fn try_close(&self, id: Id) -> bool {
+        // Only report the span closed once its last handle has been dropped
+        self.release_span(id.into_u64())
     }
 
     fn current_span(&self) -> tracing_core::span::Current {
-        // Return current span context based on our thread-local stack
-        if let Some(span_id) = self.current_span_id() {
-            if let Ok(storage) = self.span_storage.lock() {
-                if let Some(span_info) = storage.get(&span_id) {
-                    // Create a Current span with the stored metadata
-                    return tracing_core::span::Current::new(
-                        Id::from_u64(span_id),
-                        span_info.metadata,
-                    );
-                }
-            }
+        // Return current span context based on the current task's span stack
+        if let Some(span_id) = self.current_span_id()
+            && let Ok(storage) = self.span_storage.lock()
+            && let Some(span_info) = storage.get(&span_id)
+        {
+            // Create a Current span with the stored metadata
+            return tracing_core::span::Current::new(Id::from_u64(span_id), span_info.metadata);
         }
         tracing_core::span::Current::none()
     }
 
-    fn register_callsite(&self, _metadata: &'static Metadata<'static>) -> Interest {
-        // Always express interest - we handle filtering in our dispatcher
-        Interest::always()
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        // Cached by `tracing` until the dispatcher rebuilds the interest cache
+        if self.callsite_filter.enabled(metadata) {
+            Interest::always()
+        } else {
+            Interest::never()
+        }
     }
 
     fn max_level_hint(&self) -> Option<tracing_core::LevelFilter> {
-        // No filtering at this level - accept all levels
-        None
+        Some(self.callsite_filter.max_level_hint())
     }
 }
@@ -0,0 +1,241 @@
+// tests/test_env_filter.rs
+use anyhow::Result;
+use std::collections::HashMap;
+use tokio_tracer::{
+    MatchOrder, Matcher, MatcherSet, SpanSnapshot, TraceData, TraceLevel, TraceValue, TracerConfig,
+};
+use tracing::Level;
+
+// Helper to create an event with a target, optionally inside a span with fields
+fn create_test_event(level: Level, target: &str, span: Option<(&str, &str, i64)>) -> TraceData {
+    let mut event = TraceData {
+        id: 1,
+        timestamp: chrono::Local::now(),
+        level: TraceLevel(level),
+        target: target.to_string(),
+        name: "test_event".to_string(),
+        module_path: Some(target.to_string()),
+        file: None,
+        line: None,
+        message: "event".to_string(),
+        fields: HashMap::new(),
+        span_name: None,
+        span_hierarchy: None,
+        spans: Vec::new(),
+        span_event: None,
+        thread_name: None,
+        thread_id: 0,
+        task_id: None,
+    };
+    if let Some((name, field, value)) = span {
+        let fields = HashMap::from([(field.to_string(), TraceValue::I64(value))]);
+        event.fields.extend(fields.clone());
+        event.span_name = Some(name.to_string());
+        event.span_hierarchy = Some(name.to_string());
+        event.spans.push(SpanSnapshot {
+            name: name.to_string(),
+            target: target.to_string(),
+            id: 1,
+            fields,
+        });
+    }
+    event
+}
+
+// Evaluate a set the way a tab does
+fn captures(set: &MatcherSet, event: &TraceData) -> bool {
+    set.evaluate(event).is_some_and(|matcher| matcher.include)
+}
+
+#[test]
+fn test_directives_translate_to_matchers() {
+    let set = MatcherSet::from_env_filter("my_app=debug,hyper=warn,[request{id=5}]=trace").unwrap();
+
+    assert_eq!(set.order(), MatchOrder::FirstMatch);
+    let matchers = set.iter_matchers();
+    assert_eq!(matchers.len(), 3);
+    assert!(matchers.contains(&&Matcher::debug().target_pattern("my_app**")));
+    assert!(matchers.contains(&&Matcher::warn().target_pattern("hyper**")));
+    assert!(matchers.contains(&&Matcher::trace().span_field_pattern("request{id=5}")));
+
+    let captured = |level, target, span| captures(&set, &create_test_event(level, target, span));
+    assert!(captured(Level::DEBUG, "my_app::net", None));
+    assert!(!captured(Level::TRACE, "my_app::net", None));
+    assert!(captured(Level::WARN, "hyper::proto", None));
+    assert!(!captured(Level::INFO, "hyper::proto", None));
+    assert!(!captured(Level::ERROR, "tokio", None));
+
+    // Span directives reach any target inside a matching span
+    assert!(captured(Level::TRACE, "tokio", Some(("request", "id", 5))));
+    assert!(!captured(Level::TRACE, "tokio", Some(("request", "id", 6))));
+    assert!(!captured(
+        Level::TRACE,
+        "tokio",
+        Some(("response", "id", 5))
+    ));
+}
+
+// Nest spans, root first, each with its own fields, and add the event's own fields
+fn nest(mut event: TraceData, spans: &[(&str, &str, i64)], own: Option<(&str, i64)>) -> TraceData {
+    for (id, (name, field, value)) in spans.iter().enumerate() {
+        let fields = HashMap::from([(field.to_string(), TraceValue::I64(*value))]);
+        event.fields.extend(fields.clone());
+        event.spans.push(SpanSnapshot {
+            name: name.to_string(),
+            target: event.target.clone(),
+            id: id as u64 + 1,
+            fields,
+        });
+    }
+    if let Some((field, value)) = own {
+        event
+            .fields
+            .insert(field.to_string(), TraceValue::I64(value));
+    }
+    event
+}
+
+#[test]
+fn test_span_fields_are_checked_on_the_named_span() {
+    let set = MatcherSet::from_env_filter("[request{id=5}]=trace").unwrap();
+    let event = || create_test_event(Level::TRACE, "app", None);
+
+    // An inner span with the same field doesn't hide the request's own value
+    let shadowed_by_span = nest(event(), &[("request", "id", 5), ("inner", "id", 9)], None);
+    assert_eq!(shadowed_by_span.fields["id"], TraceValue::I64(9));
+    assert!(captures(&set, &shadowed_by_span));
+
+    // Nor does the event's own field stand in for the request's
+    let shadowed_by_event = nest(event(), &[("request", "id", 7)], Some(("id", 5)));
+    assert_eq!(shadowed_by_event.fields["id"], TraceValue::I64(5));
+    assert!(!captures(&set, &shadowed_by_event));
+
+    // Any request span in the ancestry can match, and so can any span without a name
+    let outer = nest(event(), &[("request", "id", 5), ("request", "id", 6)], None);
+    assert!(captures(&set, &outer));
+    let set = MatcherSet::from_env_filter("[{id=5}]=trace").unwrap();
+    assert!(captures(&set, &shadowed_by_span));
+    assert!(!captures(&set, &shadowed_by_event));
+}
+
+#[test]
+fn test_narrower_directives_override_broader_ones() {
+    let set =
+        MatcherSet::from_env_filter("info, my_app=trace, my_app::db=warn, noisy=off").unwrap();
+    let captured = |level, target| captures(&set, &create_test_event(level, target, None));
+
+    // The global default applies to everything without a directive
+    assert!(captured(Level::INFO, "tokio"));
+    assert!(!captured(Level::DEBUG, "tokio"));
+
+    // A more verbose target widens the default
+    assert!(captured(Level::TRACE, "my_app::net"));
+
+    // A less verbose target narrows what broader directives let through
+    assert!(captured(Level::WARN, "my_app::db::pool"));
+    assert!(!captured(Level::INFO, "my_app::db::pool"));
+    assert!(!captured(Level::TRACE, "my_app::db"));
+
+    // `off` silences a target entirely
+    assert!(!captured(Level::ERROR, "noisy::client"));
+
+    // Bare targets enable every level, bare levels set the default
+    let set = MatcherSet::from_env_filter("warn,my_app").unwrap();
+    assert!(captures(
+        &set,
+        &create_test_event(Level::TRACE, "my_app", None)
+    ));
+    assert!(captures(
+        &set,
+        &create_test_event(Level::WARN, "other", None)
+    ));
+    assert!(!captures(
+        &set,
+        &create_test_event(Level::INFO, "other", None)
+    ));
+
+    // Spans switched off win over targets
+    let set = MatcherSet::from_env_filter("trace,[health_check]=off").unwrap();
+    assert!(!captures(
+        &set,
+        &create_test_event(Level::ERROR, "app", Some(("health_check", "id", 1)))
+    ));
+    assert!(captures(
+        &set,
+        &create_test_event(Level::ERROR, "app", None)
+    ));
+}
+
+#[test]
+fn test_nested_targets_alternate_levels() {
+    let set = MatcherSet::from_env_filter("info,hyper=warn,hyper::client=debug").unwrap();
+    let captured = |level, target| captures(&set, &create_test_event(level, target, None));
+    assert!(captured(Level::DEBUG, "hyper::client::pool"));
+    assert!(!captured(Level::TRACE, "hyper::client::pool"));
+    assert!(captured(Level::WARN, "hyper::proto"));
+    assert!(!captured(Level::INFO, "hyper::proto"));
+    assert!(captured(Level::INFO, "tokio"));
+    assert!(!captured(Level::DEBUG, "tokio"));
+
+    // The order directives are written in doesn't matter
+    let set = MatcherSet::from_env_filter("a::b=info,a=off,trace").unwrap();
+    let captured = |level, target| captures(&set, &create_test_event(level, target, None));
+    assert!(captured(Level::INFO, "a::b"));
+    assert!(!captured(Level::DEBUG, "a::b"));
+    assert!(!captured(Level::ERROR, "a::c"));
+    assert!(captured(Level::TRACE, "b"));
+
+    // Span directives widen whatever the targets allow, and leave the rest to them
+    let set = MatcherSet::from_env_filter("warn,noisy=off,[request]=debug").unwrap();
+    let in_request = |level, target| {
+        captures(
+            &set,
+            &create_test_event(level, target, Some(("request", "id", 1))),
+        )
+    };
+    assert!(in_request(Level::DEBUG, "noisy"));
+    assert!(!in_request(Level::TRACE, "app"));
+    assert!(in_request(Level::WARN, "app"));
+}
+
+#[test]
+fn test_unsupported_directives_are_errors() {
+    let error = |filter: &str| MatcherSet::from_env_filter(filter).unwrap_err();
+
+    let invalid_level = error("my_app=loud");
+    assert_eq!(invalid_level.position, 7);
+    assert_eq!(invalid_level.message, "invalid level `loud`");
+    assert_eq!(error("my_app=debug,[request{id=5}=trace").position, 13);
+    assert_eq!(error("my_app]=debug").position, 6);
+    assert_eq!(error("=debug").position, 0);
+    assert_eq!(error("my app=debug").position, 2);
+    assert_eq!(error("[request{name=~\"^a\"}]=trace").position, 9);
+
+    assert!(MatcherSet::from_env_filter("").unwrap().is_empty());
+    assert!(MatcherSet::from_env_filter("my_app=debug,").is_ok());
+}
+
+#[test]
+fn test_config_from_environment_variable() -> Result<()> {
+    const VAR: &str = "TOKIO_TRACER_TEST_FILTER";
+
+    // SAFETY: no other test reads or writes this variable
+    unsafe { std::env::set_var(VAR, "my_app=debug,hyper=warn") };
+    let config = TracerConfig::from_env(VAR)?;
+    assert_eq!(config.tabs.len(), 1);
+    assert_eq!(config.tabs[0].name, VAR);
+    assert_eq!(config.tabs[0].matcher_set.iter_matchers().len(), 2);
+
+    unsafe { std::env::set_var(VAR, "my_app=loud") };
+    let error = TracerConfig::from_env(VAR).unwrap_err();
+    assert_eq!(format!("{error}"), format!("Failed to parse ${VAR}"));
+    assert_eq!(
+        error.root_cause().to_string(),
+        "invalid level `loud` at position 7"
+    );
+
+    unsafe { std::env::remove_var(VAR) };
+    assert!(TracerConfig::from_env(VAR).is_err());
+
+    Ok(())
+}
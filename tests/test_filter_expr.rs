@@ -0,0 +1,290 @@
+// tests/test_filter_expr.rs
+use std::collections::HashMap;
+use tokio_tracer::{
+    FilterParseError, LevelMatch, MatchOrder, Matcher, MatcherSet, TraceData, TraceLevel,
+    TraceValue,
+};
+use tracing::Level;
+
+// Helper to create an event from the parts filters look at
+fn create_test_event(level: Level, module: &str, target: &str, message: &str) -> TraceData {
+    TraceData {
+        id: 1,
+        timestamp: chrono::Local::now(),
+        level: TraceLevel(level),
+        target: target.to_string(),
+        name: "test_event".to_string(),
+        module_path: Some(module.to_string()),
+        file: Some("src/net.rs".to_string()),
+        line: Some(1),
+        message: message.to_string(),
+        fields: HashMap::new(),
+        span_name: None,
+        span_hierarchy: None,
+        spans: Vec::new(),
+        span_event: None,
+        thread_name: None,
+        thread_id: 0,
+        task_id: None,
+    }
+}
+
+#[test]
+fn test_expression_compiles_to_matchers() {
+    let set =
+        MatcherSet::parse("level>=info && module=my_app::net::* && !target=hyper::**").unwrap();
+
+    let include = Matcher::info().module_pattern("my_app::net::*");
+    let exclude = include.clone().target_pattern("hyper::**").exclude();
+    assert_eq!(set.order(), MatchOrder::ExcludesFirst);
+    let matchers = set.iter_matchers();
+    assert_eq!(matchers.len(), 2);
+    assert!(matchers.contains(&&include));
+    let parsed_exclude = matchers.iter().find(|m| !m.include).unwrap();
+    assert_eq!(*parsed_exclude, &exclude);
+    assert!(!parsed_exclude.include);
+
+    let net = create_test_event(Level::INFO, "my_app::net::tcp", "my_app", "connected");
+    let hyper = create_test_event(Level::WARN, "my_app::net::http", "hyper::proto", "reset");
+    let debug = create_test_event(Level::DEBUG, "my_app::net::tcp", "my_app", "bytes");
+    let other = create_test_event(Level::ERROR, "my_app::db", "my_app", "failed");
+    assert!(set.evaluate(&net).is_some_and(|matcher| matcher.include));
+    assert!(!set.evaluate(&hyper).is_some_and(|matcher| matcher.include));
+    assert!(!set.evaluate(&debug).is_some_and(|matcher| matcher.include));
+    assert!(!set.evaluate(&other).is_some_and(|matcher| matcher.include));
+}
+
+#[test]
+fn test_expression_criteria() {
+    let matcher = Matcher::parse(
+        r#"level=trace..debug && target=api|web && message="timed out" && field.status>=500 && field.tenant"#,
+    )
+    .unwrap();
+    assert_eq!(matcher.level, TraceLevel(Level::TRACE));
+    assert_eq!(
+        matcher.level_match,
+        LevelMatch::Between(TraceLevel(Level::DEBUG))
+    );
+    assert_eq!(matcher.target_patterns, vec!["api", "web"]);
+    assert_eq!(matcher.message_patterns, vec!["timed out"]);
+    assert_eq!(matcher.field_patterns, vec!["status >= 500", "tenant"]);
+
+    let mut event = create_test_event(Level::DEBUG, "app", "web", "request timed out");
+    event
+        .fields
+        .insert("status".to_string(), TraceValue::U64(503));
+    event.fields.insert("tenant".to_string(), "acme".into());
+    assert!(matcher.matches(&event));
+    event
+        .fields
+        .insert("status".to_string(), TraceValue::U64(200));
+    assert!(!matcher.matches(&event));
+
+    let exact = Matcher::parse("level = debug").unwrap();
+    assert_eq!(exact.level_match, LevelMatch::Exactly);
+    let at_most = Matcher::parse("level<=info && span_path=handler::**").unwrap();
+    assert_eq!(at_most.level_match, LevelMatch::AtMost);
+    assert_eq!(at_most.span_path_patterns, vec!["handler::**"]);
+
+    // `all` stands for a matcher without criteria
+    assert_eq!(Matcher::parse("all").unwrap(), Matcher::trace());
+    let everything_but_hyper = MatcherSet::parse("all && !target=hyper*").unwrap();
+    assert!(
+        everything_but_hyper
+            .evaluate(&create_test_event(Level::TRACE, "app", "app", "kept"))
+            .is_some_and(|matcher| matcher.include)
+    );
+    assert!(
+        !everything_but_hyper
+            .evaluate(&create_test_event(Level::TRACE, "app", "hyper", "dropped"))
+            .is_some_and(|matcher| matcher.include)
+    );
+    assert!(MatcherSet::parse("").unwrap().is_empty());
+}
+
+#[test]
+fn test_expression_display_round_trips() {
+    for expression in [
+        "level>=info && module=my_app::net::* && !target=hyper*",
+        r#"level=debug && message="connection \"reset\"" || target=api|web"#,
+        "level=warn..trace && field.tenant=acme* && field.status>=500 || !(target=noisy)",
+        "all || !(level<=debug && ancestor=health_check)",
+        "level>=info && span_field=request{id=5,user=3} || span_field=\"*{ tenant = acme }\"",
+    ] {
+        let set = MatcherSet::parse(expression).unwrap();
+        let rendered = set.to_string();
+        let reparsed = MatcherSet::parse(&rendered)
+            .unwrap_or_else(|error| panic!("`{rendered}` did not parse: {error}"));
+        assert_eq!(reparsed.to_string(), rendered);
+
+        let mut original = set.iter_matchers();
+        let mut round_trip = reparsed.iter_matchers();
+        original.sort_by_key(|m| m.to_string());
+        round_trip.sort_by_key(|m| m.to_string());
+        assert_eq!(original, round_trip, "{expression} rendered as {rendered}");
+    }
+
+    // Matchers built in code come back equal however their field patterns were spaced
+    for matcher in [
+        Matcher::info().field_pattern("status>=500"),
+        Matcher::info().field_patterns(["tenant = acme*", "region", "code!=5"]),
+        Matcher::debug().field_pattern("op < =5"),
+    ] {
+        let rendered = matcher.to_string();
+        assert_eq!(rendered.parse::<Matcher>().unwrap(), matcher, "{rendered}");
+    }
+    assert_eq!(
+        Matcher::info().field_pattern("status>=500"),
+        Matcher::info().field_pattern("status >= 500")
+    );
+
+    assert_eq!(
+        MatcherSet::parse("level>=info && module=my_app::net::* && !target=hyper*")
+            .unwrap()
+            .to_string(),
+        "level>=info && module=my_app::net::* || !(level>=info && module=my_app::net::* && target=hyper*)"
+    );
+
+    // Matchers built in code render too
+    let matcher = Matcher::warn()
+        .all_modules()
+        .message_contains("disk full")
+        .exclude();
+    assert_eq!(
+        matcher.to_string(),
+        r#"!(level>=warn && module=* && message="disk full")"#
+    );
+    assert_eq!(Matcher::parse(&matcher.to_string()).unwrap(), matcher);
+}
+
+#[test]
+fn test_expression_errors_report_positions() {
+    let error = |expression: &str| MatcherSet::parse(expression).unwrap_err();
+
+    assert_eq!(
+        error("level>=info && modul=app"),
+        FilterParseError {
+            position: 15,
+            message: "unknown key `modul`".to_string()
+        }
+    );
+    assert_eq!(error("level>=loud").position, 7);
+    assert_eq!(error("level>=info &&").position, 14);
+    assert_eq!(error("module=app & target=x").position, 11);
+    assert_eq!(error(r#"message="unterminated"#).position, 8);
+    assert_eq!(error("module=a && module=b").position, 12);
+    assert_eq!(error("!(target=a && !target=b)").position, 14);
+    assert_eq!(error("level>info").position, 5);
+    assert_eq!(error("field.status>=500|600").position, 17);
+
+    let error = error("target=");
+    assert_eq!(error.to_string(), "expected a value at position 7");
+
+    assert!(Matcher::parse("target=a || target=b").is_err());
+    assert!("level>=info".parse::<Matcher>().is_ok());
+    assert!("level>=info || !(target=x)".parse::<MatcherSet>().is_ok());
+}
+
+#[test]
+fn test_negations_stay_within_their_clause() {
+    // Excluding hyper from the first clause would also silence what the second includes
+    let error =
+        MatcherSet::parse("level>=info && !target=hyper::** || target=hyper::client").unwrap_err();
+    assert_eq!(error.position, 15);
+    assert!(error.message.contains("excludes apply to the whole set"));
+    assert_eq!(
+        MatcherSet::parse("target=api || level>=warn && !(module=noisy && target=x)")
+            .unwrap_err()
+            .position,
+        29
+    );
+
+    // A single including clause can negate freely, alongside set-wide exclusions
+    let set = MatcherSet::parse("level>=info && !target=hyper::** || !(module=noisy)").unwrap();
+    let hyper = create_test_event(Level::INFO, "app", "hyper::client", "request");
+    let app = create_test_event(Level::INFO, "app", "app", "request");
+    let noisy = create_test_event(Level::INFO, "noisy", "app", "request");
+    assert!(!set.evaluate(&hyper).is_some_and(|matcher| matcher.include));
+    assert!(set.evaluate(&app).is_some_and(|matcher| matcher.include));
+    assert!(!set.evaluate(&noisy).is_some_and(|matcher| matcher.include));
+
+    // Several including clauses are fine without negations inside them
+    let set = MatcherSet::parse("level>=warn || target=hyper::client || !(module=noisy)").unwrap();
+    assert!(set.evaluate(&hyper).is_some_and(|matcher| matcher.include));
+    assert!(!set.evaluate(&app).is_some_and(|matcher| matcher.include));
+}
+
+#[test]
+fn test_first_match_sets_round_trip() {
+    let set = MatcherSet::from_matchers([
+        Matcher::trace().module_pattern("db::slow"),
+        Matcher::trace().module_pattern("db::*").exclude(),
+        Matcher::info(),
+    ])
+    .first_match();
+    let rendered = set.to_string();
+    assert_eq!(
+        rendered,
+        "first: module=db::slow || !(module=db::*) || level>=info"
+    );
+
+    let reparsed = MatcherSet::parse(&rendered).unwrap();
+    assert_eq!(reparsed.order(), MatchOrder::FirstMatch);
+    assert_eq!(reparsed.iter_matchers(), set.iter_matchers());
+    assert_eq!(reparsed.to_string(), rendered);
+    let slow = create_test_event(Level::DEBUG, "db::slow", "db::slow", "query");
+    let fast = create_test_event(Level::INFO, "db::fast", "db::fast", "query");
+    assert!(
+        reparsed
+            .evaluate(&slow)
+            .is_some_and(|matcher| matcher.include)
+    );
+    assert!(
+        !reparsed
+            .evaluate(&fast)
+            .is_some_and(|matcher| matcher.include)
+    );
+
+    // Env filters come out first-match too
+    let set = MatcherSet::from_env_filter("my_app=debug,hyper=warn").unwrap();
+    let reparsed = MatcherSet::parse(&set.to_string()).unwrap();
+    assert_eq!(reparsed.order(), MatchOrder::FirstMatch);
+    assert_eq!(reparsed.iter_matchers(), set.iter_matchers());
+
+    assert_eq!(MatcherSet::empty().first_match().to_string(), "first:");
+    assert_eq!(
+        MatcherSet::parse("first:").unwrap().order(),
+        MatchOrder::FirstMatch
+    );
+
+    // A negation inside a clause only shadows the clauses after it
+    assert!(MatcherSet::parse("first: target=api || level>=info && !target=hyper*").is_ok());
+    assert_eq!(
+        MatcherSet::parse("first: level>=info && !target=hyper* || target=api")
+            .unwrap_err()
+            .position,
+        22
+    );
+}
+
+#[test]
+fn test_bare_regex_values_run_to_the_end_of_their_term() {
+    let mut event = create_test_event(Level::INFO, "app", "app", "request");
+    event.fields.insert("x".to_string(), "ab".into());
+
+    let matcher = Matcher::parse("field.x ~ ^a(b|c)$").unwrap();
+    assert_eq!(matcher.field_patterns, vec!["x ~ ^a(b|c)$"]);
+    assert!(matcher.matches(&event));
+
+    let set = MatcherSet::parse("level>=info && !(field.x ~ ^a(b|c)$) || !(target=noisy)").unwrap();
+    assert!(!set.evaluate(&event).is_some_and(|matcher| matcher.include));
+    let set = MatcherSet::parse("field.x ~ ^a(b|c)$ && level>=info || target=web").unwrap();
+    assert!(set.evaluate(&event).is_some_and(|matcher| matcher.include));
+
+    // An unbalanced group asks for quotes instead of failing further along
+    let error = MatcherSet::parse("field.x ~ ^a(b").unwrap_err();
+    assert_eq!(error.position, 10);
+    assert!(error.message.contains("quote it"), "{error}");
+    let matcher = Matcher::parse(r#"field.x ~ "^a(b""#).unwrap();
+    assert_eq!(matcher.field_patterns, vec!["x ~ ^a(b"]);
+}
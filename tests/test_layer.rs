@@ -0,0 +1,160 @@
+// tests/test_layer.rs
+#![cfg(feature = "layer")]
+
+use anyhow::Result;
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicU64, AtomicUsize, Ordering},
+};
+use tokio::sync::mpsc;
+use tokio_tracer::{
+    Matcher, SpanEventKind, SpanEvents, TraceEvent, TraceValue, Tracer, TracerConfig, TracerLayer,
+};
+use tracing::{Event, Subscriber, info, info_span, warn};
+use tracing_subscriber::{
+    Registry,
+    layer::{Context, Layer, SubscriberExt},
+};
+
+// A second layer in the stack, standing in for fmt / OpenTelemetry layers
+#[derive(Clone, Default)]
+struct CountingLayer {
+    events: Arc<AtomicUsize>,
+}
+
+impl<S: Subscriber> Layer<S> for CountingLayer {
+    fn on_event(&self, _event: &Event<'_>, _ctx: Context<'_, S>) {
+        self.events.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+// Helper to collect everything the layer has sent so far
+fn drain(rx: &mut mpsc::UnboundedReceiver<TraceEvent>) -> Vec<TraceEvent> {
+    let mut events = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        events.push(event);
+    }
+    events
+}
+
+#[test]
+fn test_layer_composes_with_other_layers() {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let counting = CountingLayer::default();
+    let subscriber = Registry::default()
+        .with(TracerLayer::new(tx, Arc::new(AtomicU64::new(0))))
+        .with(counting.clone());
+
+    tracing::subscriber::with_default(subscriber, || {
+        let outer = info_span!("request", request_id = 42);
+        let _outer = outer.enter();
+        let inner = info_span!("db_query", table = "users");
+        let _inner = inner.enter();
+        info!(rows = 3, "query finished");
+    });
+
+    // Both layers saw the event
+    assert_eq!(counting.events.load(Ordering::SeqCst), 1);
+
+    let events = drain(&mut rx);
+    assert_eq!(events.len(), 1);
+    let event = &events[0];
+    assert_eq!(event.message, "query finished");
+    assert_eq!(event.span_name.as_deref(), Some("db_query"));
+    assert_eq!(event.span_hierarchy.as_deref(), Some("request::db_query"));
+    assert_eq!(event.fields.get("rows"), Some(&TraceValue::I64(3)));
+    assert_eq!(event.fields.get("table"), Some(&TraceValue::from("users")));
+    assert_eq!(event.fields.get("request_id"), Some(&TraceValue::I64(42)));
+    assert_eq!(
+        event.span("request").unwrap().fields.get("request_id"),
+        Some(&TraceValue::I64(42))
+    );
+    assert_eq!(event.spans.len(), 2);
+}
+
+#[test]
+fn test_layer_span_lifecycle_and_records() {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let layer =
+        TracerLayer::new(tx, Arc::new(AtomicU64::new(0))).with_span_events(SpanEvents::FULL);
+    let subscriber = Registry::default().with(layer);
+
+    tracing::subscriber::with_default(subscriber, || {
+        let span = info_span!("job", status = tracing::field::Empty);
+        span.record("status", "done");
+        let clone = span.clone();
+        drop(span);
+        clone.in_scope(|| info!("inside"));
+    });
+
+    let events = drain(&mut rx);
+    let kinds: Vec<_> = events
+        .iter()
+        .map(|event| event.span_event.map(|span_event| span_event.kind))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            Some(SpanEventKind::New),
+            Some(SpanEventKind::Enter),
+            None,
+            Some(SpanEventKind::Exit),
+            Some(SpanEventKind::Close),
+        ]
+    );
+
+    // Recorded values are merged into the event and the close record
+    assert_eq!(
+        events[2].fields.get("status"),
+        Some(&TraceValue::from("done"))
+    );
+    let close = events.last().unwrap();
+    assert_eq!(close.fields.get("status"), Some(&TraceValue::from("done")));
+    assert!(close.span_event.unwrap().busy.is_some());
+}
+
+#[tokio::test]
+async fn test_tracer_layer_feeds_dispatcher_tabs() -> Result<()> {
+    let tracer = Tracer::new_with_config(
+        TracerConfig::empty()
+            .with_tab("warnings", Matcher::warn().all_modules())
+            .with_tab(
+                "requests",
+                Matcher::trace().all_modules().span_pattern("request"),
+            ),
+    );
+
+    let captured = Arc::new(Mutex::new(Vec::new()));
+    let captured_clone = captured.clone();
+    tracer
+        .set_callback(move |event, tabs| {
+            let tabs = tabs.iter().map(|tab| tab.to_string()).collect::<Vec<_>>();
+            captured_clone
+                .lock()
+                .unwrap()
+                .push((event.message.clone(), tabs));
+        })?
+        .await??;
+
+    let subscriber = Registry::default().with(tracer.layer());
+    tracing::subscriber::with_default(subscriber, || {
+        warn!("disk almost full");
+        info_span!("request").in_scope(|| info!("handling request"));
+        info!("not captured");
+    });
+
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+    let captured = captured.lock().unwrap();
+    assert_eq!(
+        *captured,
+        vec![
+            ("disk almost full".to_string(), vec!["warnings".to_string()]),
+            ("handling request".to_string(), vec!["requests".to_string()]),
+        ]
+    );
+    assert_eq!(tracer.get_captured_count(), 2);
+    assert_eq!(tracer.get_dropped_count(), 1);
+
+    Ok(())
+}
@@ -0,0 +1,116 @@
+// tests/test_log_bridge.rs
+#![cfg(feature = "log")]
+
+use anyhow::Result;
+use std::{sync::Arc, time::Duration};
+use tokio::sync::Mutex;
+use tokio_tracer::{Matcher, TraceEvent, TraceValue, Tracer, TracerConfig};
+use tracing::Level;
+
+// Helper to collect every captured event with the tabs that captured it
+async fn capture_all(tracer: &Tracer) -> Result<Arc<Mutex<Vec<(TraceEvent, Vec<String>)>>>> {
+    let captured = Arc::new(Mutex::new(Vec::new()));
+    let captured_clone = captured.clone();
+    tracer
+        .set_callback(move |event, tabs| {
+            let tabs = tabs.iter().map(|tab| tab.to_string()).collect();
+            captured_clone.try_lock().unwrap().push((event, tabs));
+        })?
+        .await??;
+    Ok(captured)
+}
+
+#[tokio::test]
+async fn test_log_records_are_matched_like_events() -> Result<()> {
+    let tracer = Tracer::new_with_config(
+        TracerConfig::empty()
+            .with_tab("http", Matcher::debug().module_pattern("hyper::*"))
+            .with_tab(
+                "legacy",
+                Matcher::info().all_modules().target_pattern("legacy"),
+            ),
+    );
+    let captured = capture_all(&tracer).await?;
+    let logger = tracer.log_bridge();
+
+    let log = |level, target: &str, module: &str, message: &str| {
+        log::Log::log(
+            &logger,
+            &log::Record::builder()
+                .level(level)
+                .target(target)
+                .module_path(Some(module))
+                .file(Some("src/proto.rs"))
+                .line(Some(12))
+                .args(format_args!("{message}"))
+                .build(),
+        );
+    };
+    log(log::Level::Debug, "hyper", "hyper::proto", "parsed headers");
+    log(log::Level::Trace, "hyper", "hyper::proto", "too verbose");
+    log(log::Level::Warn, "legacy", "old_crate", "deprecated call");
+    log(log::Level::Info, "other", "other_crate", "not captured");
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let captured = captured.lock().await;
+    let summary: Vec<_> = captured
+        .iter()
+        .map(|(event, tabs)| (event.message.as_str(), tabs.clone()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("parsed headers", vec!["http".to_string()]),
+            ("deprecated call", vec!["legacy".to_string()]),
+        ]
+    );
+
+    let event = &captured[0].0;
+    assert_eq!(event.level.0, Level::DEBUG);
+    assert_eq!(event.target, "hyper");
+    assert_eq!(event.module_path.as_deref(), Some("hyper::proto"));
+    assert_eq!(event.file.as_deref(), Some("src/proto.rs"));
+    assert_eq!(event.line, Some(12));
+
+    // The TRACE record is too verbose for every tab, so the bridge never sent it
+    assert!(
+        captured
+            .iter()
+            .all(|(event, _)| event.message != "too verbose")
+    );
+
+    // Only the record from `other` reached the dispatcher without a tab to take it
+    assert_eq!(tracer.get_dropped_count(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_installed_log_bridge_keeps_key_values() -> Result<()> {
+    let tracer = Tracer::new_with_config(
+        TracerConfig::empty().with_tab("all", Matcher::trace().all_modules()),
+    );
+    let captured = capture_all(&tracer).await?;
+    tracer.install_log_bridge()?;
+
+    log::warn!(target: "legacy", status = 503, retry = true, latency = 1.5, path = "/api"; "request failed");
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let captured = captured.lock().await;
+    let event = &captured[0].0;
+    assert_eq!(event.message, "request failed");
+    assert_eq!(event.level.0, Level::WARN);
+    assert_eq!(event.target, "legacy");
+    assert_eq!(event.module_path.as_deref(), Some(module_path!()));
+    assert_eq!(event.fields.get("status"), Some(&TraceValue::I64(503)));
+    assert_eq!(event.fields.get("retry"), Some(&TraceValue::Bool(true)));
+    assert_eq!(event.fields.get("latency"), Some(&TraceValue::F64(1.5)));
+    assert_eq!(event.fields.get("path"), Some(&TraceValue::from("/api")));
+
+    // Only one logger can be installed per process
+    assert!(tracer.install_log_bridge().is_err());
+
+    Ok(())
+}
@@ -3,7 +3,10 @@
 use chrono::Local;
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio_tracer::{Matcher, MatcherSet, TraceData, TraceLevel, matches};
+use tokio_tracer::{
+    Criterion, LevelMatch, MatchOrder, Matcher, MatcherSet, PatternError, TraceData, TraceLevel,
+    TraceValue, TracerConfig, Verdict, matches,
+};
 use tracing::Level;
 
 // Helper to create a test trace event with specific properties
@@ -28,9 +31,18 @@ fn create_test_event(
         file: file.map(|s| s.to_string()),
         line,
         message: event.to_string(),
-        fields: fields.unwrap_or_default(),
+        fields: fields
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(key, value)| (key, value.into()))
+            .collect(),
         span_name: span_name.map(|s| s.to_string()),
         span_hierarchy: span_name.map(|s| s.to_string()), // Initialize with the same value as span_name
+        spans: Vec::new(),
+        span_event: None,
+        thread_name: None,
+        thread_id: 0,
+        task_id: None,
     }
 }
 
@@ -605,3 +617,748 @@ fn test_builder_pattern() {
             .contains(&"new_pattern2".to_string())
     );
 }
+
+#[test]
+fn test_thread_based_matchering() {
+    let mut worker_event = create_test_event(
+        1,
+        Level::INFO,
+        "Worker event",
+        Some("app"),
+        None,
+        None,
+        None,
+        None,
+    );
+    worker_event.thread_name = Some("tokio-runtime-worker".to_string());
+    worker_event.thread_id = 7;
+
+    let mut unnamed_event = worker_event.clone();
+    unnamed_event.thread_name = None;
+    unnamed_event.thread_id = 12;
+
+    // Thread names match as globs
+    let worker_matcher = Matcher::info().all_modules().thread_pattern("tokio-*");
+    assert!(worker_matcher.matches(&worker_event));
+    assert!(!worker_matcher.matches(&unnamed_event));
+
+    // Thread ids match as well, which is the only handle on unnamed threads
+    let id_matcher = Matcher::info().all_modules().thread_patterns(["7", "12"]);
+    assert!(id_matcher.matches(&worker_event));
+    assert!(id_matcher.matches(&unnamed_event));
+
+    let other_matcher = Matcher::info().all_modules().thread_pattern("blocking-*");
+    assert!(!other_matcher.matches(&worker_event));
+}
+
+#[test]
+fn test_field_based_matchering() {
+    let mut event = create_test_event(
+        1,
+        Level::WARN,
+        "Request failed",
+        Some("api::handler"),
+        None,
+        None,
+        None,
+        None,
+    );
+    event
+        .fields
+        .insert("status".to_string(), TraceValue::U64(503));
+    event.fields.insert("tenant".to_string(), "acme-eu".into());
+    event
+        .fields
+        .insert("latency".to_string(), TraceValue::F64(0.25));
+    event.fields.insert(
+        "request".to_string(),
+        TraceValue::Map(vec![("method".to_string(), "POST".into())]),
+    );
+    let field_matcher = |pattern: &str| Matcher::info().all_modules().field_pattern(pattern);
+
+    // Existence
+    assert!(field_matcher("tenant").matches(&event));
+    assert!(!field_matcher("user_id").matches(&event));
+
+    // Glob and regex equality
+    assert!(field_matcher("tenant = acme*").matches(&event));
+    assert!(field_matcher("tenant=acme-eu").matches(&event));
+    assert!(!field_matcher("tenant = globex*").matches(&event));
+    assert!(field_matcher("tenant != globex*").matches(&event));
+    assert!(field_matcher("tenant ~ ^acme-(eu|us)$").matches(&event));
+    assert!(field_matcher("status = 503").matches(&event));
+    assert!(field_matcher("request.method = POST").matches(&event));
+
+    // Numeric comparisons
+    assert!(field_matcher("status >= 500").matches(&event));
+    assert!(field_matcher("status > 502").matches(&event));
+    assert!(!field_matcher("status < 500").matches(&event));
+    assert!(field_matcher("status <= 503").matches(&event));
+    assert!(field_matcher("latency < 0.5").matches(&event));
+    assert!(!field_matcher("tenant > 5").matches(&event));
+
+    // Malformed predicates never match
+    assert!(!field_matcher("status >= lots").matches(&event));
+    assert!(!field_matcher("= acme").matches(&event));
+
+    // Every field pattern must hold
+    let both = Matcher::info()
+        .all_modules()
+        .field_patterns(["tenant = acme*", "status >= 500"]);
+    assert!(both.matches(&event));
+    let one = both.clone().field_pattern("status < 500");
+    assert!(!one.matches(&event));
+
+    // Field patterns serialize alongside the other patterns
+    let config = TracerConfig::empty().with_tab("acme_errors", both.clone());
+    let json = serde_json::to_value(&config).unwrap();
+    assert_eq!(
+        json["tabs"][0]["matcher_set"]["matchers"][0]["field_patterns"],
+        serde_json::json!(["tenant = acme*", "status >= 500"])
+    );
+    let round_trip: TracerConfig = serde_json::from_value(json).unwrap();
+    let matcher = round_trip.tabs[0].matcher_set.iter_matchers()[0].clone();
+    assert_eq!(matcher, both);
+    assert!(matcher.matches(&event));
+}
+
+#[test]
+fn test_span_path_patterns_without_snapshots() {
+    // Events built without span snapshots fall back to the joined hierarchy
+    let mut event = create_test_event(
+        1,
+        Level::INFO,
+        "nested",
+        Some("app"),
+        None,
+        None,
+        Some("db_query"),
+        None,
+    );
+    event.span_hierarchy = Some("handle_request::authorize::db_query".to_string());
+
+    let matcher = Matcher::info().all_modules();
+    assert!(
+        matcher
+            .clone()
+            .ancestor_pattern("authorize")
+            .matches(&event)
+    );
+    assert!(
+        matcher
+            .clone()
+            .span_path_pattern("handle_request::**::db_query")
+            .matches(&event)
+    );
+    assert!(
+        !matcher
+            .clone()
+            .span_path_pattern("authorize::db_query")
+            .matches(&event)
+    );
+
+    // Events outside any span only match paths that allow no spans at all
+    event.span_name = None;
+    event.span_hierarchy = None;
+    assert!(!matcher.clone().ancestor_pattern("*").matches(&event));
+    assert!(matcher.clone().span_path_pattern("**").matches(&event));
+    assert!(!matcher.span_path_pattern("*").matches(&event));
+}
+
+#[test]
+fn test_level_match_modes() {
+    let event_at =
+        |level| create_test_event(1, level, "leveled", Some("app"), None, None, None, None);
+    let levels = [
+        Level::ERROR,
+        Level::WARN,
+        Level::INFO,
+        Level::DEBUG,
+        Level::TRACE,
+    ];
+    let captured = |matcher: &Matcher| -> Vec<Level> {
+        levels
+            .into_iter()
+            .filter(|level| matcher.matches(&event_at(*level)))
+            .collect()
+    };
+
+    // The default keeps "this level or more severe"
+    let at_least = Matcher::info().all_modules();
+    assert_eq!(at_least.level_match, LevelMatch::AtLeast);
+    assert_eq!(
+        captured(&at_least),
+        [Level::ERROR, Level::WARN, Level::INFO]
+    );
+    assert_eq!(at_least.max_level(), Level::INFO);
+
+    let exactly = Matcher::debug().all_modules().exactly();
+    assert_eq!(captured(&exactly), [Level::DEBUG]);
+    assert_eq!(exactly.max_level(), Level::DEBUG);
+
+    let at_most = Matcher::info().all_modules().at_most();
+    assert_eq!(
+        captured(&at_most),
+        [Level::INFO, Level::DEBUG, Level::TRACE]
+    );
+    assert_eq!(at_most.max_level(), Level::TRACE);
+
+    // Ranges are inclusive and work in either order
+    let range = Matcher::range(Level::TRACE, Level::DEBUG).all_modules();
+    assert_eq!(captured(&range), [Level::DEBUG, Level::TRACE]);
+    assert_eq!(range.max_level(), Level::TRACE);
+    let reversed = Matcher::range(Level::WARN, Level::INFO).all_modules();
+    assert_eq!(captured(&reversed), [Level::WARN, Level::INFO]);
+    assert_eq!(reversed.max_level(), Level::INFO);
+
+    // Modes are part of a matcher's identity
+    assert_ne!(exactly, Matcher::debug().all_modules());
+    assert_eq!(at_least, Matcher::info().all_modules().exactly().at_least());
+
+    // Modes serialize in configs, and configs without one keep the default
+    let config = TracerConfig::empty()
+        .with_tab("debug_only", exactly.clone())
+        .with_tab("verbose", range.clone());
+    let json = serde_json::to_value(&config).unwrap();
+    assert_eq!(
+        json["tabs"][1]["matcher_set"]["matchers"][0]["level_match"],
+        serde_json::json!({ "Between": "DEBUG" })
+    );
+    let round_trip: TracerConfig = serde_json::from_value(json).unwrap();
+    assert_eq!(round_trip.tabs[0].matcher_set.iter_matchers()[0], &exactly);
+    assert_eq!(round_trip.tabs[1].matcher_set.iter_matchers()[0], &range);
+
+    let legacy: Matcher = serde_json::from_value(serde_json::json!({
+        "level": "WARN",
+        "include": true,
+        "module_patterns": ["*"],
+        "file_patterns": [],
+        "span_patterns": [],
+        "target_patterns": []
+    }))
+    .unwrap();
+    assert_eq!(legacy.level_match, LevelMatch::AtLeast);
+    assert_eq!(captured(&legacy), [Level::ERROR, Level::WARN]);
+}
+
+#[test]
+fn test_message_based_matchering() {
+    let event = create_test_event(
+        1,
+        Level::WARN,
+        "Retry 3 of 5: upstream timeout\nafter 30s",
+        Some("api::client"),
+        None,
+        None,
+        None,
+        None,
+    );
+    let message_matcher = || Matcher::info().all_modules();
+
+    // Substring mode is the default
+    assert!(message_matcher().message_pattern("timeout").matches(&event));
+    assert!(
+        message_matcher()
+            .message_contains("upstream")
+            .matches(&event)
+    );
+    assert!(
+        !message_matcher()
+            .message_contains("Timeout")
+            .matches(&event)
+    );
+
+    // Globs cover the whole message, across lines
+    assert!(message_matcher().message_glob("Retry *").matches(&event));
+    assert!(
+        message_matcher()
+            .message_glob("*timeout*30s")
+            .matches(&event)
+    );
+    assert!(!message_matcher().message_glob("timeout*").matches(&event));
+    assert!(
+        message_matcher()
+            .message_pattern("glob:*after 30s")
+            .matches(&event)
+    );
+
+    // Regexes search the message
+    assert!(
+        message_matcher()
+            .message_regex(r"Retry \d+ of \d+")
+            .matches(&event)
+    );
+    assert!(
+        message_matcher()
+            .message_pattern("regex:(?i)TIMEOUT")
+            .matches(&event)
+    );
+    assert!(
+        !message_matcher()
+            .message_regex(r"^upstream")
+            .matches(&event)
+    );
+    assert!(!message_matcher().message_regex("(unclosed").matches(&event));
+
+    // Any message pattern may match, and the other criteria still apply
+    let either = message_matcher().message_patterns(["refused", "timeout"]);
+    assert!(either.matches(&event));
+    let wrong_module = either.clone().module_patterns(["db::*"]);
+    assert!(!wrong_module.matches(&event));
+}
+
+#[test]
+fn test_compiled_patterns_match_like_globs() {
+    let event = create_test_event(
+        1,
+        Level::INFO,
+        "compiled",
+        Some("app::db::pool"),
+        Some("src/db/pool.rs"),
+        Some(7),
+        Some("checkout"),
+        None,
+    );
+
+    // Exact, prefix and wildcard globs
+    assert!(
+        Matcher::info()
+            .module_pattern("app::db::pool")
+            .matches(&event)
+    );
+    assert!(Matcher::info().module_pattern("app::**").matches(&event));
+    assert!(Matcher::info().module_pattern("**::pool").matches(&event));
+    assert!(
+        Matcher::info()
+            .module_pattern("app::*::pool")
+            .matches(&event)
+    );
+    assert!(!Matcher::info().module_pattern("app::db").matches(&event));
+    assert!(Matcher::info().file_pattern("src/**/*.rs").matches(&event));
+    // Invalid regex syntax never matches
+    assert!(!Matcher::info().module_pattern("re:app::(").matches(&event));
+
+    // Builder methods recompile on a matcher that has already matched
+    let matcher = Matcher::info().module_pattern("other::*");
+    assert!(!matcher.matches(&event));
+    let matcher = matcher.module_pattern("app::**");
+    assert!(matcher.matches(&event));
+    let matcher = matcher.module_patterns(["other::*"]);
+    assert!(!matcher.matches(&event));
+
+    // Direct edits take effect once recompiled
+    let mut matcher = matcher;
+    matcher.module_patterns = vec!["app::db::*".to_string()];
+    matcher.recompile_patterns();
+    assert!(matcher.matches(&event));
+
+    // Deserialized matchers compile their patterns too
+    let json = serde_json::to_string(&matcher).unwrap();
+    let round_trip: Matcher = serde_json::from_str(&json).unwrap();
+    assert!(round_trip.matches(&event));
+}
+
+/// The heavy-load scenario from the tracer tests: a thousand `test_module` events
+/// checked against its tab plus a few others
+fn heavy_load_scenario() -> (Vec<Matcher>, Vec<TraceData>) {
+    let matchers = vec![
+        Matcher::debug().module_pattern("test_module*"),
+        Matcher::info()
+            .module_patterns(["api::*", "web::*"])
+            .target_pattern("http"),
+        Matcher::trace()
+            .all_modules()
+            .file_pattern("src/*.rs")
+            .span_pattern("request_*"),
+        Matcher::warn().module_pattern("test_module").exclude(),
+    ];
+    let events = (0..1000)
+        .map(|i| {
+            create_test_event(
+                i,
+                Level::DEBUG,
+                &format!("Test event {i}"),
+                Some("test_module"),
+                Some("test.rs"),
+                Some(i as u32),
+                None,
+                None,
+            )
+        })
+        .collect();
+    (matchers, events)
+}
+
+fn compiled_hits(matchers: &[Matcher], events: &[TraceData]) -> usize {
+    events
+        .iter()
+        .map(|event| matchers.iter().filter(|m| m.matches(event)).count())
+        .sum()
+}
+
+/// Compiles every pattern for every event through `matches`, as a reference for `Matcher`
+fn per_call_hits(matchers: &[Matcher], events: &[TraceData]) -> usize {
+    let any = |patterns: &[String], value: &str| {
+        patterns.is_empty() || patterns.iter().any(|p| matches(p, value))
+    };
+    events
+        .iter()
+        .map(|event| {
+            matchers
+                .iter()
+                .filter(|matcher| {
+                    matcher.matches_level(event.level.0)
+                        && any(
+                            &matcher.module_patterns,
+                            event.module_path.as_deref().unwrap(),
+                        )
+                        && any(&matcher.file_patterns, event.file.as_deref().unwrap())
+                        && any(
+                            &matcher.span_patterns,
+                            event.span_name.as_deref().unwrap_or_default(),
+                        )
+                        && any(&matcher.target_patterns, &event.target)
+                })
+                .count()
+        })
+        .sum()
+}
+
+#[test]
+fn test_compiled_patterns_agree_with_per_call_matching() {
+    let (matchers, events) = heavy_load_scenario();
+    let hits = compiled_hits(&matchers, &events);
+    assert!(hits > 0);
+    assert_eq!(hits, per_call_hits(&matchers, &events));
+}
+
+#[test]
+fn test_first_match_order() {
+    let rules = [
+        Matcher::trace().module_pattern("db::slow_query"),
+        Matcher::trace().module_pattern("db::*").exclude(),
+        Matcher::info().all_modules(),
+    ];
+    let event =
+        |level, module| create_test_event(1, level, "query", Some(module), None, None, None, None);
+    let decision = |set: &MatcherSet, level, module| {
+        set.evaluate(&event(level, module))
+            .map(|matcher| matcher.include)
+    };
+
+    let ordered = MatcherSet::from_matchers(rules.clone()).first_match();
+    assert_eq!(ordered.order(), MatchOrder::FirstMatch);
+    assert_eq!(
+        decision(&ordered, Level::TRACE, "db::slow_query"),
+        Some(true)
+    );
+    assert_eq!(decision(&ordered, Level::ERROR, "db::pool"), Some(false));
+    assert_eq!(decision(&ordered, Level::INFO, "api"), Some(true));
+    assert_eq!(decision(&ordered, Level::DEBUG, "api"), None);
+
+    // By default any exclude wins, so the slow query rule never gets a say
+    let unordered = MatcherSet::from_matchers(rules.clone());
+    assert_eq!(unordered.order(), MatchOrder::ExcludesFirst);
+    assert_eq!(
+        decision(&unordered, Level::TRACE, "db::slow_query"),
+        Some(false)
+    );
+    assert_eq!(decision(&unordered, Level::INFO, "api"), Some(true));
+
+    // Matchers keep their order, and re-adding one replaces it in place
+    let mut reordered = ordered.clone();
+    reordered.add_matcher(Matcher::trace().module_pattern("db::*").exclude());
+    let modules: Vec<_> = reordered
+        .iter_matchers()
+        .iter()
+        .map(|matcher| matcher.module_patterns.clone())
+        .collect();
+    assert_eq!(
+        modules,
+        vec![vec!["db::slow_query"], vec!["db::*"], vec!["*"]]
+    );
+    assert!(reordered.remove_matcher(&rules[0]));
+    assert_eq!(
+        decision(&reordered, Level::TRACE, "db::slow_query"),
+        Some(false)
+    );
+
+    // Order survives serialization, and older configs default to excludes first
+    let json = serde_json::to_string(&ordered).unwrap();
+    let restored: MatcherSet = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.order(), MatchOrder::FirstMatch);
+    assert_eq!(restored.iter_matchers(), ordered.iter_matchers(),);
+    let mut legacy: serde_json::Value = serde_json::from_str(&json).unwrap();
+    legacy.as_object_mut().unwrap().remove("order");
+    let legacy: MatcherSet = serde_json::from_value(legacy).unwrap();
+    assert_eq!(legacy.order(), MatchOrder::ExcludesFirst);
+}
+
+#[test]
+fn test_include_and_exclude_of_same_criteria_both_kept() {
+    let noisy = Matcher::info().module_pattern("noisy");
+    assert_ne!(noisy, noisy.clone().exclude());
+
+    let mut set = MatcherSet::from_matchers([noisy.clone().exclude(), noisy.clone()]);
+    assert_eq!(set.iter_matchers().len(), 2);
+    let event = create_test_event(
+        1,
+        Level::INFO,
+        "chatter",
+        Some("noisy"),
+        None,
+        None,
+        None,
+        None,
+    );
+    assert_eq!(set.evaluate(&event).map(|m| m.include), Some(false));
+
+    // First match keeps the rules in the order they were written
+    let ordered = set.clone().first_match();
+    assert_eq!(ordered.evaluate(&event).map(|m| m.include), Some(false));
+    let ordered = MatcherSet::from_matchers([noisy.clone(), noisy.clone().exclude()]).first_match();
+    assert_eq!(ordered.evaluate(&event).map(|m| m.include), Some(true));
+
+    // Removing one leaves the other
+    assert!(set.remove_matcher(&noisy));
+    assert_eq!(set.iter_matchers(), vec![&noisy.clone().exclude()]);
+    assert!(!set.remove_matcher(&noisy));
+}
+
+#[test]
+fn test_explain_reports_deciding_matcher_and_failed_criteria() {
+    let set = MatcherSet::from_matchers([
+        Matcher::info().module_pattern("api::*"),
+        Matcher::trace()
+            .all_modules()
+            .file_pattern("src/health.rs")
+            .exclude(),
+        Matcher::warn().all_modules().field_pattern("status >= 500"),
+    ]);
+    let event = |level, module, file| {
+        create_test_event(
+            1,
+            level,
+            "request",
+            Some(module),
+            Some(file),
+            None,
+            None,
+            None,
+        )
+    };
+
+    let captured = set.explain(&event(Level::INFO, "api::users", "src/users.rs"));
+    assert_eq!(captured.verdict, Verdict::Captured);
+    assert_eq!(captured.decided_by, Some(0));
+    let failed: Vec<_> = captured.matchers.iter().map(|m| m.failed).collect();
+    assert_eq!(
+        failed,
+        vec![None, Some(Criterion::File), Some(Criterion::Level)]
+    );
+
+    // Excludes decide before includes in the default order
+    let silenced = set.explain(&event(Level::ERROR, "api::health", "src/health.rs"));
+    assert_eq!(silenced.verdict, Verdict::Silenced);
+    assert_eq!(silenced.decided_by, Some(1));
+    assert_eq!(silenced.decisive_matcher(), Some(set.iter_matchers()[1]));
+
+    let dropped = set.explain(&event(Level::ERROR, "db", "src/db.rs"));
+    assert_eq!(dropped.verdict, Verdict::Dropped);
+    assert_eq!(dropped.decided_by, None);
+    let failed: Vec<_> = dropped.matchers.iter().map(|m| m.failed).collect();
+    assert_eq!(
+        failed,
+        vec![
+            Some(Criterion::Module),
+            Some(Criterion::File),
+            Some(Criterion::Field(0))
+        ]
+    );
+    assert_eq!(
+        dropped.to_string(),
+        "dropped, no matcher matched\n  \
+         `level>=info && module=api::*` failed on module\n  \
+         `!(module=* && file=src/health.rs)` failed on file\n  \
+         `level>=warn && module=* && field.status>=500` failed on field `status >= 500`"
+    );
+    assert_eq!(
+        captured.to_string().lines().next(),
+        Some("captured by `level>=info && module=api::*`")
+    );
+
+    // First-match sets report the first matcher that matched
+    let ordered = set.clone().first_match();
+    let explanation = ordered.explain(&event(Level::ERROR, "api::health", "src/health.rs"));
+    assert_eq!(explanation.verdict, Verdict::Captured);
+    assert_eq!(explanation.decided_by, Some(0));
+    assert!(
+        MatcherSet::empty()
+            .explain(&event(Level::ERROR, "api", "src/api.rs"))
+            .to_string()
+            .starts_with("dropped, no matchers")
+    );
+}
+
+#[test]
+fn test_glob_syntax() {
+    // Regex metacharacters match themselves
+    assert!(matches("my_app::handler(v2)", "my_app::handler(v2)"));
+    assert!(matches("src/a+b.rs", "src/a+b.rs"));
+    assert!(!matches("src/a+b.rs", "src/aab.rs"));
+    assert!(matches("app.*", "app.rs"));
+    assert!(!matches("app.*", "apprs"));
+
+    // `*` stays within a segment, `**` crosses separators
+    assert!(matches("app::*", "app::db"));
+    assert!(!matches("app::*", "app::db::pool"));
+    assert!(matches("app::**", "app::db::pool"));
+    assert!(matches("src/*.rs", "src/lib.rs"));
+    assert!(!matches("src/*.rs", "src/db/pool.rs"));
+    assert!(matches("src/**/*.rs", "src/db/pool.rs"));
+    assert!(matches("src/**/*.rs", "src/lib.rs"));
+    assert!(matches("**::pool", "pool"));
+    assert!(matches("**::pool", "app::db::pool"));
+    assert!(!matches("**::pool", "app::db::spool"));
+    assert!(matches("*", "app::db::pool"));
+    assert!(!matches("*database*", "app::database"));
+    assert!(matches("**database**", "app::database"));
+
+    // `?` and classes match one character, never a separator
+    assert!(matches("v?", "v2"));
+    assert!(!matches("v?", "v"));
+    assert!(!matches("a?b", "a/b"));
+    assert!(matches("worker-[0-9]", "worker-7"));
+    assert!(!matches("worker-[0-9]", "worker-x"));
+    assert!(matches("worker-[!0-9]", "worker-x"));
+    assert!(matches("[]a]", "]"));
+    assert!(matches("v[-+]", "v-"));
+    assert!(!matches("a[!x]b", "a:b"));
+
+    // Escapes make wildcards literal
+    assert!(matches(r"a\*", "a*"));
+    assert!(!matches(r"a\*", "ab"));
+    assert!(matches(r"file\[1\]", "file[1]"));
+
+    // `re:` searches with a raw regex
+    assert!(matches("re:^app::(db|net)", "app::db::pool"));
+    assert!(matches("re:pool$", "app::db::pool"));
+    assert!(!matches("re:^db", "app::db::pool"));
+
+    // Malformed patterns never match
+    assert!(!matches("[abc", "a"));
+    assert!(!matches(r"abc\", "abc"));
+    assert!(!matches("re:(", "("));
+
+    // Message and field value globs run across separators
+    let mut fields = HashMap::new();
+    fields.insert("path".to_string(), "/api/users/1".to_string());
+    let event = create_test_event(
+        1,
+        Level::INFO,
+        "GET /api/users/1 timed out",
+        Some("app"),
+        None,
+        None,
+        None,
+        Some(fields),
+    );
+    assert!(
+        Matcher::info()
+            .message_glob("GET * timed out")
+            .matches(&event)
+    );
+    assert!(
+        Matcher::info()
+            .field_pattern("path = /api/*")
+            .matches(&event)
+    );
+    assert!(
+        !Matcher::info()
+            .field_pattern("path = /web/*")
+            .matches(&event)
+    );
+}
+
+#[test]
+fn test_validate_reports_invalid_patterns() {
+    assert!(
+        Matcher::info()
+            .module_patterns(["app::**", "re:^db"])
+            .file_pattern("src/[a-z]*.rs")
+            .message_regex("timed? out")
+            .field_pattern("status >= 500")
+            .validate()
+            .is_ok()
+    );
+
+    let matcher = Matcher::info()
+        .module_patterns(["app::*", "app::[db"])
+        .target_pattern("re:(")
+        .message_regex("[")
+        .span_path_pattern(r"request::**::db\")
+        .field_patterns(["status >= high", "tenant = acme", "= 5"])
+        .span_field_patterns(["request{id=5}", "request"]);
+    let errors = matcher.validate().unwrap_err();
+    let found: Vec<_> = errors
+        .iter()
+        .map(|error| (error.criterion, error.pattern.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (Criterion::Module, "app::[db"),
+            (Criterion::Target, "re:("),
+            (Criterion::SpanPath, r"request::**::db\"),
+            (Criterion::Message, "regex:["),
+            (Criterion::Field(0), "status >= high"),
+            (Criterion::Field(2), "= 5"),
+            (Criterion::SpanField(1), "request"),
+        ]
+    );
+    assert_eq!(
+        errors[0],
+        PatternError {
+            criterion: Criterion::Module,
+            pattern: "app::[db".to_string(),
+            reason: "unclosed `[` at 5".to_string(),
+        }
+    );
+    assert_eq!(
+        errors[1].to_string(),
+        "invalid target pattern `re:(`: invalid regex, unclosed group"
+    );
+    assert_eq!(errors[4].reason, "`>=` needs a number, found `high`");
+    assert_eq!(errors[5].reason, "missing field name");
+
+    // Sets collect the errors of every matcher
+    let set = MatcherSet::from_matchers([
+        Matcher::info().module_pattern("[a"),
+        Matcher::debug().all_modules(),
+        Matcher::warn().file_pattern("b\\").exclude(),
+    ]);
+    let errors = set.validate().unwrap_err();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[1].criterion, Criterion::File);
+    assert!(
+        MatcherSet::from_matcher(Matcher::debug().all_modules())
+            .validate()
+            .is_ok()
+    );
+
+    // Config files name the tab and pattern that failed
+    let config = TracerConfig::empty()
+        .with_tab("ok", Matcher::info().all_modules())
+        .with_tab("broken", Matcher::info().module_pattern("app::[db"));
+    let json = serde_json::to_string(&config).unwrap();
+    let error = serde_json::from_str::<TracerConfig>(&json)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.contains("Tab 'broken' has invalid module pattern `app::[db`: unclosed `[` at 5"),
+        "{error}"
+    );
+    let valid = TracerConfig::empty().with_tab("ok", Matcher::info().module_pattern("app::*"));
+    let json = serde_json::to_string(&valid).unwrap();
+    assert!(serde_json::from_str::<TracerConfig>(&json).is_ok());
+}
@@ -0,0 +1,188 @@
+// tests/test_panic_hook.rs
+use anyhow::Result;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio_tracer::{Matcher, PANIC_TARGET, TraceEvent, Tracer, TracerConfig};
+use tracing::Level;
+
+// The panic hook is process-wide, so tests installing one take turns
+static HOOK_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+// Helper to collect every captured event with the tabs that captured it
+async fn capture_all(tracer: &Tracer) -> Result<Arc<Mutex<Vec<(TraceEvent, Vec<String>)>>>> {
+    let captured = Arc::new(Mutex::new(Vec::new()));
+    let captured_clone = captured.clone();
+    tracer
+        .set_callback(move |event, tabs| {
+            let tabs = tabs.iter().map(|tab| tab.to_string()).collect();
+            captured_clone.lock().unwrap().push((event, tabs));
+        })?
+        .await??;
+    Ok(captured)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_panics_reach_callback_before_previous_hook() -> Result<()> {
+    let _hook = HOOK_LOCK.lock().await;
+    let tracer = Tracer::new_with_config(
+        TracerConfig::empty().with_tab("panics", Matcher::error().target_pattern(PANIC_TARGET)),
+    );
+
+    let captured: Arc<Mutex<Vec<TraceEvent>>> = Arc::new(Mutex::new(Vec::new()));
+    let captured_clone = captured.clone();
+    tracer
+        .set_callback(move |event, _tabs| captured_clone.lock().unwrap().push(event))?
+        .await??;
+
+    // Stands in for the default hook, recording what had been delivered when it ran
+    let seen_by_previous_hook = Arc::new(Mutex::new(None));
+    let seen_clone = seen_by_previous_hook.clone();
+    let captured_clone = captured.clone();
+    std::panic::set_hook(Box::new(move |_info| {
+        *seen_clone.lock().unwrap() = Some(captured_clone.lock().unwrap().len());
+    }));
+    tracer.install_panic_hook();
+
+    let task = tokio::spawn(async {
+        let code = 42;
+        panic!("worker failed with code {code}");
+    });
+    assert!(task.await.unwrap_err().is_panic());
+
+    // Restore the default hook for anything else in this process
+    let _ = std::panic::take_hook();
+
+    assert_eq!(*seen_by_previous_hook.lock().unwrap(), Some(1));
+
+    let captured = captured.lock().unwrap();
+    let event = &captured[0];
+    assert_eq!(event.level.0, Level::ERROR);
+    assert_eq!(event.target, PANIC_TARGET);
+    assert_eq!(event.message, "worker failed with code 42");
+    assert!(
+        event
+            .file
+            .as_deref()
+            .unwrap()
+            .ends_with("test_panic_hook.rs")
+    );
+    assert!(event.line.is_some());
+    assert!(event.thread_name.is_some());
+    assert!(event.task_id.is_some());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_panics_reach_callback_on_current_thread_runtime() -> Result<()> {
+    let _hook = HOOK_LOCK.lock().await;
+    let tracer = Tracer::new_with_config(
+        TracerConfig::empty().with_tab("panics", Matcher::error().target_pattern(PANIC_TARGET)),
+    );
+    let captured = capture_all(&tracer).await?;
+
+    // The dispatcher can't run while the hook does, so the record must not wait for it
+    let seen_by_previous_hook = Arc::new(Mutex::new(Vec::new()));
+    let seen_clone = seen_by_previous_hook.clone();
+    let captured_clone = captured.clone();
+    std::panic::set_hook(Box::new(move |_info| {
+        let delivered = captured_clone.lock().unwrap().len();
+        seen_clone.lock().unwrap().push(delivered);
+    }));
+    tracer.install_panic_hook();
+
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async {
+            let task = tokio::task::spawn_local(async { panic!("local task failed") });
+            assert!(task.await.unwrap_err().is_panic());
+        })
+        .await;
+    let task = tokio::spawn(async { panic!("task failed") });
+    assert!(task.await.unwrap_err().is_panic());
+
+    let _ = std::panic::take_hook();
+
+    assert_eq!(*seen_by_previous_hook.lock().unwrap(), vec![1, 2]);
+    let messages: Vec<_> = captured
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(event, _)| event.message.clone())
+        .collect();
+    assert_eq!(messages, vec!["local task failed", "task failed"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_panics_have_no_module_path() -> Result<()> {
+    let _hook = HOOK_LOCK.lock().await;
+    let tracer = Tracer::new_with_config(
+        TracerConfig::empty()
+            .with_tab("all", Matcher::error().all_modules())
+            .with_tab("files", Matcher::error().file_pattern("tests/**"))
+            .with_tab(
+                "modules",
+                Matcher::error().module_pattern("test_panic_hook::**"),
+            ),
+    );
+    let captured = capture_all(&tracer).await?;
+
+    std::panic::set_hook(Box::new(|_info| {}));
+    tracer.install_panic_hook();
+    let task = tokio::spawn(async { panic!("no module") });
+    assert!(task.await.unwrap_err().is_panic());
+    let _ = std::panic::take_hook();
+
+    // Only tabs that don't need a module path see the panic
+    let captured = captured.lock().unwrap();
+    let (event, tabs) = &captured[0];
+    assert_eq!(event.module_path, None);
+    let mut tabs = tabs.clone();
+    tabs.sort();
+    assert_eq!(tabs, vec!["all", "files"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_panic_hook_does_not_keep_dispatcher_alive() -> Result<()> {
+    let _hook = HOOK_LOCK.lock().await;
+    let tracer = Tracer::new_with_config(
+        TracerConfig::empty().with_tab("panics", Matcher::error().target_pattern(PANIC_TARGET)),
+    );
+
+    // The callback lives as long as the dispatcher does
+    let alive = Arc::new(());
+    let alive_clone = alive.clone();
+    tracer
+        .set_callback(move |_event, _tabs| {
+            let _ = &alive_clone;
+        })?
+        .await??;
+
+    std::panic::set_hook(Box::new(|_info| {}));
+    tracer.install_panic_hook();
+    drop(tracer);
+
+    let mut waited = Duration::ZERO;
+    while Arc::strong_count(&alive) > 1 && waited < Duration::from_secs(5) {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        waited += Duration::from_millis(10);
+    }
+    assert_eq!(
+        Arc::strong_count(&alive),
+        1,
+        "dispatcher outlived its tracer"
+    );
+
+    // With the dispatcher gone the hook only runs the previous one
+    let task = tokio::spawn(async { panic!("after shutdown") });
+    assert!(task.await.unwrap_err().is_panic());
+    let _ = std::panic::take_hook();
+
+    Ok(())
+}
@@ -0,0 +1,835 @@
+// tests/test_subscriber.rs
+use std::sync::{Arc, atomic::AtomicU64};
+use tokio::sync::mpsc;
+use tokio_tracer::{Matcher, SpanEventKind, SpanEvents, TraceEvent, TraceValue, TracingSubscriber};
+use tracing::{Dispatch, Instrument, Level, info, info_span, instrument::WithSubscriber, span};
+use valuable::{
+    EnumDef, Enumerable, Fields, NamedField, NamedValues, StructDef, Structable, Valuable, Value,
+    Variant, VariantDef, Visit,
+};
+
+// Helper to build a subscriber wired to a channel we can drain directly
+fn create_subscriber(
+    span_events: SpanEvents,
+) -> (TracingSubscriber, mpsc::UnboundedReceiver<TraceEvent>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let subscriber =
+        TracingSubscriber::new(tx, Arc::new(AtomicU64::new(1))).with_span_events(span_events);
+    (subscriber, rx)
+}
+
+// Helper to collect everything the subscriber has sent so far
+fn drain(rx: &mut mpsc::UnboundedReceiver<TraceEvent>) -> Vec<TraceEvent> {
+    let mut events = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        events.push(event);
+    }
+    events
+}
+
+#[test]
+fn test_span_events_disabled_by_default() {
+    let (subscriber, mut rx) = create_subscriber(SpanEvents::NONE);
+
+    tracing::subscriber::with_default(subscriber, || {
+        let span = span!(Level::INFO, "quiet_span");
+        let _guard = span.enter();
+        info!("inside span");
+    });
+
+    let events = drain(&mut rx);
+    assert_eq!(events.len(), 1, "Only the regular event should be sent");
+    assert!(!events[0].is_span_event());
+}
+
+#[test]
+fn test_span_lifecycle_events() {
+    let (subscriber, mut rx) = create_subscriber(SpanEvents::FULL);
+
+    tracing::subscriber::with_default(subscriber, || {
+        let outer = span!(Level::INFO, "outer", request_id = 7);
+        let _outer_guard = outer.enter();
+        {
+            let inner = span!(Level::DEBUG, "inner");
+            let _inner_guard = inner.enter();
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            info!("working");
+        }
+    });
+
+    let events = drain(&mut rx);
+    let lifecycle: Vec<(String, SpanEventKind)> = events
+        .iter()
+        .filter_map(|event| {
+            event
+                .span_event
+                .map(|span_event| (event.name.clone(), span_event.kind))
+        })
+        .collect();
+
+    assert_eq!(
+        lifecycle,
+        vec![
+            ("outer".to_string(), SpanEventKind::New),
+            ("outer".to_string(), SpanEventKind::Enter),
+            ("inner".to_string(), SpanEventKind::New),
+            ("inner".to_string(), SpanEventKind::Enter),
+            ("inner".to_string(), SpanEventKind::Exit),
+            ("inner".to_string(), SpanEventKind::Close),
+            ("outer".to_string(), SpanEventKind::Exit),
+            ("outer".to_string(), SpanEventKind::Close),
+        ]
+    );
+
+    // The inner close record carries timings and its place in the hierarchy
+    let inner_close = events
+        .iter()
+        .find(|event| {
+            event.name == "inner" && event.span_event.map(|s| s.kind) == Some(SpanEventKind::Close)
+        })
+        .unwrap();
+    let timings = inner_close.span_event.unwrap();
+    assert!(timings.busy.unwrap() >= std::time::Duration::from_millis(5));
+    assert!(timings.idle.is_some());
+    assert_eq!(inner_close.span_name.as_deref(), Some("inner"));
+    assert_eq!(inner_close.span_hierarchy.as_deref(), Some("outer::inner"));
+    assert!(inner_close.message.starts_with("close"));
+
+    // Span fields travel with the lifecycle records
+    let outer_new = events
+        .iter()
+        .find(|event| event.name == "outer" && event.is_span_event())
+        .unwrap();
+    assert_eq!(
+        outer_new.fields.get("request_id"),
+        Some(&TraceValue::I64(7))
+    );
+}
+
+#[test]
+fn test_span_events_respect_span_patterns() {
+    let (subscriber, mut rx) = create_subscriber(SpanEvents::LIFECYCLE);
+
+    tracing::subscriber::with_default(subscriber, || {
+        let db = span!(Level::INFO, "database_query");
+        let _db_guard = db.enter();
+        drop(_db_guard);
+        drop(db);
+
+        let http = span!(Level::INFO, "http_request");
+        drop(http);
+    });
+
+    let events = drain(&mut rx);
+    assert_eq!(events.len(), 4, "new + close for each span");
+
+    let matcher = Matcher::trace().all_modules().span_pattern("database*");
+    let matched: Vec<_> = events.iter().filter(|e| matcher.matches(e)).collect();
+    assert_eq!(matched.len(), 2);
+    assert!(matched.iter().all(|e| e.name == "database_query"));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_span_context_across_multi_thread_runtime() {
+    let (subscriber, mut rx) = create_subscriber(SpanEvents::NONE);
+    let dispatch = Dispatch::new(subscriber);
+
+    const TASKS: u64 = 32;
+    let mut handles = Vec::new();
+    for task in 0..TASKS {
+        let future = async move {
+            // Created inside the task so it is registered with our dispatch
+            let outer = info_span!("outer", task_id = task);
+            async move {
+                for step in 0..5u64 {
+                    info!(task, step, "before yield");
+                    // Force the task back through the scheduler so it can resume elsewhere
+                    tokio::task::yield_now().await;
+
+                    let inner = info_span!("inner", task_id = task);
+                    async move {
+                        tokio::task::yield_now().await;
+                        info!(task, step, "inside inner");
+                    }
+                    .instrument(inner)
+                    .await;
+                }
+            }
+            .instrument(outer)
+            .await;
+        };
+
+        handles.push(tokio::spawn(future.with_subscriber(dispatch.clone())));
+    }
+    for handle in handles {
+        handle.await.unwrap();
+    }
+
+    // Every span closed with its task, so nothing is left in storage
+    let subscriber = dispatch.downcast_ref::<TracingSubscriber>().unwrap();
+    assert_eq!(subscriber._live_span_count_for_testing(), 0);
+
+    // Event emitted outside of every task must not inherit a stale span
+    tracing::dispatcher::with_default(&dispatch, || info!("after tasks"));
+
+    let events = drain(&mut rx);
+    assert_eq!(events.len() as u64, TASKS * 10 + 1);
+
+    for event in &events {
+        if event.message == "after tasks" {
+            assert_eq!(event.span_name, None);
+            assert_eq!(event.span_hierarchy, None);
+            continue;
+        }
+
+        // The span fields merged into the event must belong to the emitting task
+        assert_eq!(
+            event.fields.get("task_id"),
+            event.fields.get("task"),
+            "Event {:?} picked up another task's span",
+            event.message
+        );
+
+        if event.message == "inside inner" {
+            assert_eq!(event.span_name.as_deref(), Some("inner"));
+            assert_eq!(event.span_hierarchy.as_deref(), Some("outer::inner"));
+        } else {
+            assert_eq!(event.span_name.as_deref(), Some("outer"));
+            assert_eq!(event.span_hierarchy.as_deref(), Some("outer"));
+        }
+    }
+}
+
+#[test]
+fn test_exit_from_another_context_only_pops_an_unambiguous_entry() {
+    let (subscriber, mut rx) = create_subscriber(SpanEvents::NONE);
+    let dispatch = Dispatch::new(subscriber);
+    let span = tracing::dispatcher::with_default(&dispatch, || info_span!("shared"));
+    let id = span.id().unwrap();
+
+    // Each thread is its own context outside a runtime
+    // Entered from two contexts at once, an exit elsewhere can't tell whose it is
+    let (first_entered, second_entered) = (std::sync::Barrier::new(2), std::sync::Barrier::new(2));
+    let (exited, done) = (std::sync::Barrier::new(3), std::sync::Barrier::new(3));
+    std::thread::scope(|s| {
+        for entered in [&first_entered, &second_entered] {
+            let (dispatch, id) = (&dispatch, &id);
+            let (exited, done) = (&exited, &done);
+            s.spawn(move || {
+                dispatch.enter(id);
+                entered.wait();
+                exited.wait();
+                tracing::dispatcher::with_default(dispatch, || info!("still inside"));
+                done.wait();
+                dispatch.exit(id);
+            });
+        }
+        s.spawn(|| {
+            first_entered.wait();
+            second_entered.wait();
+            dispatch.exit(&id);
+            exited.wait();
+            done.wait();
+        });
+    });
+    let events = drain(&mut rx);
+    assert_eq!(events.len(), 2);
+    for event in &events {
+        assert_eq!(event.span_name.as_deref(), Some("shared"));
+    }
+
+    // Held by one context only, the moved guard's exit finds it
+    let entered = std::sync::Barrier::new(2);
+    let exited = std::sync::Barrier::new(2);
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            dispatch.enter(&id);
+            entered.wait();
+            exited.wait();
+            tracing::dispatcher::with_default(&dispatch, || info!("after exit"));
+        });
+        s.spawn(|| {
+            entered.wait();
+            dispatch.exit(&id);
+            exited.wait();
+        });
+    });
+    let events = drain(&mut rx);
+    assert_eq!(events[0].span_name, None);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_explicit_parent_spans() {
+    let (subscriber, mut rx) = create_subscriber(SpanEvents::NONE);
+    let dispatch = Dispatch::new(subscriber);
+
+    tokio::spawn(
+        async {
+            let request = info_span!("request");
+            let current = info_span!("current");
+            let _guard = current.enter();
+
+            // Explicit parents win over whatever is currently entered
+            let child = info_span!(parent: &request, "child");
+            info!(parent: &child, "explicit parent");
+            info!(parent: None, "explicit root");
+            info!("contextual");
+        }
+        .with_subscriber(dispatch),
+    )
+    .await
+    .unwrap();
+
+    let events = drain(&mut rx);
+    let hierarchy_of = |message: &str| {
+        events
+            .iter()
+            .find(|event| event.message == message)
+            .and_then(|event| event.span_hierarchy.clone())
+    };
+
+    assert_eq!(
+        hierarchy_of("explicit parent").as_deref(),
+        Some("request::child")
+    );
+    assert_eq!(hierarchy_of("explicit root"), None);
+    assert_eq!(hierarchy_of("contextual").as_deref(), Some("current"));
+}
+
+#[test]
+fn test_span_survives_until_last_clone_is_dropped() {
+    let (subscriber, mut rx) = create_subscriber(SpanEvents::LIFECYCLE);
+    let dispatch = Dispatch::new(subscriber);
+    let live_spans = || {
+        dispatch
+            .downcast_ref::<TracingSubscriber>()
+            .unwrap()
+            ._live_span_count_for_testing()
+    };
+
+    tracing::dispatcher::with_default(&dispatch, || {
+        let original = info_span!("shared", request_id = 42);
+        let clone = original.clone();
+
+        // Dropping the first handle must not discard the span's data
+        drop(original);
+        assert_eq!(live_spans(), 1);
+        clone.in_scope(|| info!("through clone"));
+
+        drop(clone);
+        assert_eq!(live_spans(), 0, "Last handle should free the span");
+    });
+
+    let events = drain(&mut rx);
+    let event = events
+        .iter()
+        .find(|event| event.message == "through clone")
+        .unwrap();
+    assert_eq!(event.span_name.as_deref(), Some("shared"));
+    assert_eq!(event.fields.get("request_id"), Some(&TraceValue::I64(42)));
+
+    // Close is reported exactly once, after the last handle went away
+    let closes: Vec<_> = events
+        .iter()
+        .filter(|event| event.span_event.map(|s| s.kind) == Some(SpanEventKind::Close))
+        .collect();
+    assert_eq!(closes.len(), 1);
+    assert_eq!(closes[0].id, events.last().unwrap().id);
+}
+
+#[test]
+fn test_child_span_keeps_parent_alive() {
+    let (subscriber, mut rx) = create_subscriber(SpanEvents::NONE);
+    let dispatch = Dispatch::new(subscriber);
+    let live_spans = || {
+        dispatch
+            .downcast_ref::<TracingSubscriber>()
+            .unwrap()
+            ._live_span_count_for_testing()
+    };
+
+    tracing::dispatcher::with_default(&dispatch, || {
+        let parent = info_span!("parent", tenant = "acme");
+        let child = parent.in_scope(|| info_span!("child"));
+
+        // The parent's handle is gone but the child still needs its ancestry
+        drop(parent);
+        assert_eq!(live_spans(), 2);
+        child.in_scope(|| info!("orphaned child"));
+
+        drop(child);
+        assert_eq!(
+            live_spans(),
+            0,
+            "Closing the child should release the parent"
+        );
+    });
+
+    let events = drain(&mut rx);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].span_hierarchy.as_deref(), Some("parent::child"));
+}
+
+#[test]
+fn test_typed_field_values() {
+    let (subscriber, mut rx) = create_subscriber(SpanEvents::NONE);
+
+    tracing::subscriber::with_default(subscriber, || {
+        let span = info_span!("request", attempt = 2u64);
+        span.in_scope(|| {
+            info!(
+                status = 503,
+                latency = 1.5,
+                cached = false,
+                tenant = "acme",
+                path = ?"/api",
+                "request failed"
+            );
+        });
+    });
+
+    let events = drain(&mut rx);
+    let fields = &events[0].fields;
+    assert_eq!(fields.get("status"), Some(&TraceValue::I64(503)));
+    assert_eq!(fields.get("latency"), Some(&TraceValue::F64(1.5)));
+    assert_eq!(fields.get("cached"), Some(&TraceValue::Bool(false)));
+    assert_eq!(
+        fields.get("tenant"),
+        Some(&TraceValue::String("acme".into()))
+    );
+    assert_eq!(
+        fields.get("path"),
+        Some(&TraceValue::Debug("\"/api\"".into()))
+    );
+    assert_eq!(fields.get("attempt"), Some(&TraceValue::U64(2)));
+    assert_eq!(
+        fields.get("status").and_then(TraceValue::as_f64),
+        Some(503.0)
+    );
+
+    // JSON output keeps the native types
+    let json = serde_json::to_value(&*events[0]).unwrap();
+    assert_eq!(json["fields"]["status"], serde_json::json!(503));
+    assert_eq!(json["fields"]["latency"], serde_json::json!(1.5));
+    assert_eq!(json["fields"]["cached"], serde_json::json!(false));
+    assert_eq!(json["fields"]["tenant"], serde_json::json!("acme"));
+
+    let round_trip: tokio_tracer::TraceData = serde_json::from_value(json).unwrap();
+    assert_eq!(round_trip.fields.get("status"), Some(&TraceValue::I64(503)));
+    assert_eq!(round_trip.fields.get("attempt"), Some(&TraceValue::I64(2)));
+    assert_eq!(
+        round_trip.fields.get("cached"),
+        Some(&TraceValue::Bool(false))
+    );
+
+    // Unsigned and debug values lose their variant on the way back
+    assert_ne!(round_trip.fields.get("attempt"), fields.get("attempt"));
+    assert_eq!(
+        round_trip.fields.get("path"),
+        Some(&TraceValue::String("\"/api\"".into()))
+    );
+    assert_ne!(round_trip.fields.get("path"), fields.get("path"));
+    let large: TraceValue = serde_json::from_value(serde_json::json!(u64::MAX)).unwrap();
+    assert_eq!(large, TraceValue::U64(u64::MAX));
+}
+
+#[test]
+fn test_events_carry_every_ancestor_span() {
+    let (subscriber, mut rx) = create_subscriber(SpanEvents::NONE);
+
+    tracing::subscriber::with_default(subscriber, || {
+        let request = info_span!("request", request_id = 42, stage = "outer");
+        let _request = request.enter();
+        let query = info_span!(target: "db", "query", table = "users", stage = "inner");
+        let _query = query.enter();
+        info!(rows = 3, "query finished");
+    });
+
+    let events = drain(&mut rx);
+    let event = &events[0];
+
+    // Each span keeps its own fields, root first
+    let names: Vec<_> = event.spans.iter().map(|span| span.name.as_str()).collect();
+    assert_eq!(names, vec!["request", "query"]);
+    assert_eq!(event.spans[1].target, "db");
+    assert_ne!(event.spans[0].id, event.spans[1].id);
+    assert_eq!(
+        event.span("request").unwrap().fields.get("request_id"),
+        Some(&TraceValue::I64(42))
+    );
+    assert_eq!(event.span("query").unwrap().fields.get("request_id"), None);
+    assert_eq!(event.span_hierarchy.as_deref(), Some("request::query"));
+
+    // Outer span fields are no longer lost when merged, inner spans win on conflicts
+    assert_eq!(event.fields.get("request_id"), Some(&TraceValue::I64(42)));
+    assert_eq!(event.fields.get("table"), Some(&TraceValue::from("users")));
+    assert_eq!(event.fields.get("stage"), Some(&TraceValue::from("inner")));
+    assert_eq!(event.fields.get("rows"), Some(&TraceValue::I64(3)));
+
+    let json = serde_json::to_value(&**event).unwrap();
+    assert_eq!(json["spans"][0]["fields"]["request_id"], 42);
+    let round_trip: tokio_tracer::TraceData = serde_json::from_value(json).unwrap();
+    assert_eq!(round_trip.spans, event.spans);
+}
+
+#[test]
+fn test_span_path_matching_sees_every_ancestor() {
+    let (subscriber, mut rx) = create_subscriber(SpanEvents::NONE);
+
+    tracing::subscriber::with_default(subscriber, || {
+        let request = info_span!("handle_request");
+        let _request = request.enter();
+        info!("accepted");
+        let auth = info_span!("authorize");
+        let _auth = auth.enter();
+        let query = info_span!("db_query");
+        let _query = query.enter();
+        info!("looked up user");
+        let decode = info_span!("decode_row");
+        let _decode = decode.enter();
+        info!("decoded row");
+    });
+
+    let events = drain(&mut rx);
+    let event = |message: &str| {
+        events
+            .iter()
+            .find(|event| event.message == message)
+            .unwrap()
+            .clone()
+    };
+    let (accepted, looked_up, decoded) = (
+        event("accepted"),
+        event("looked up user"),
+        event("decoded row"),
+    );
+    let span_matcher = || Matcher::trace().all_modules();
+
+    // The innermost span alone misses nested events
+    let innermost = span_matcher().span_pattern("handle_request");
+    assert!(innermost.matches(&accepted));
+    assert!(!innermost.matches(&looked_up));
+
+    // Any ancestor counts
+    let inside_request = span_matcher().ancestor_pattern("handle_*");
+    assert!(inside_request.matches(&accepted));
+    assert!(inside_request.matches(&looked_up));
+    assert!(inside_request.matches(&decoded));
+    assert!(!span_matcher().ancestor_pattern("render").matches(&decoded));
+
+    // `*` is exactly one span
+    let one_between = span_matcher().span_path_pattern("handle_request::*::db_query");
+    assert!(one_between.matches(&looked_up));
+    assert!(!one_between.matches(&decoded));
+    assert!(!one_between.matches(&accepted));
+
+    // `**` is any depth, including none
+    let anywhere_below = span_matcher().span_path_pattern("handle_request::**");
+    assert!(anywhere_below.matches(&accepted));
+    assert!(anywhere_below.matches(&decoded));
+    let query_anywhere = span_matcher().span_path_pattern("**::db_query::**");
+    assert!(query_anywhere.matches(&looked_up));
+    assert!(query_anywhere.matches(&decoded));
+    assert!(!query_anywhere.matches(&accepted));
+    let globbed = span_matcher().span_path_pattern("handle_*::**::decode_*");
+    assert!(globbed.matches(&decoded));
+    assert!(!globbed.matches(&looked_up));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_events_record_thread_and_task() {
+    let (subscriber, mut rx) = create_subscriber(SpanEvents::NONE);
+    let dispatch = Dispatch::new(subscriber);
+
+    let thread_dispatch = dispatch.clone();
+    std::thread::Builder::new()
+        .name("contention-probe".to_string())
+        .spawn(move || {
+            tracing::dispatcher::with_default(&thread_dispatch, || info!("from named thread"))
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+
+    let task = tokio::spawn(async { info!("from task") }.with_subscriber(dispatch));
+    let task_id = task.id().to_string();
+    task.await.unwrap();
+
+    let events = drain(&mut rx);
+    let thread_event = events
+        .iter()
+        .find(|event| event.message == "from named thread")
+        .unwrap();
+    assert_eq!(
+        thread_event.thread_name.as_deref(),
+        Some("contention-probe")
+    );
+    assert_eq!(thread_event.task_id, None);
+    assert!(thread_event.format_with_thread().contains(&format!(
+        "[thread:contention-probe#{}]",
+        thread_event.thread_id
+    )));
+
+    let task_event = events
+        .iter()
+        .find(|event| event.message == "from task")
+        .unwrap();
+    assert_eq!(task_event.task_id.map(|id| id.to_string()), Some(task_id));
+    assert_ne!(task_event.thread_id, thread_event.thread_id);
+    assert!(task_event.format_with_thread().contains(" task:"));
+
+    // Threads can be matched by name or id
+    let matcher = Matcher::trace()
+        .all_modules()
+        .thread_pattern("contention-*");
+    assert!(matcher.matches(thread_event));
+    assert!(!matcher.matches(task_event));
+    let by_id = Matcher::trace()
+        .all_modules()
+        .thread_pattern(task_event.thread_id.to_string());
+    assert!(by_id.matches(task_event));
+}
+
+// Error wrapping another error, to build a multi-level source chain
+#[derive(Debug)]
+struct WrappedError {
+    message: &'static str,
+    source: Box<dyn std::error::Error + Send + Sync>,
+}
+
+impl std::fmt::Display for WrappedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message)
+    }
+}
+
+impl std::error::Error for WrappedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&*self.source)
+    }
+}
+
+#[test]
+fn test_errors_recorded_with_source_chain() {
+    let (subscriber, mut rx) = create_subscriber(SpanEvents::NONE);
+    let error = WrappedError {
+        message: "startup failed",
+        source: Box::new(WrappedError {
+            message: "could not load config",
+            source: Box::new(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                "permission denied",
+            )),
+        }),
+    };
+
+    tracing::subscriber::with_default(subscriber, || {
+        let span = info_span!("boot", cause = &error as &dyn std::error::Error);
+        let _enter = span.enter();
+        tracing::error!(error = &error as &dyn std::error::Error, "giving up");
+    });
+
+    let events = drain(&mut rx);
+    let event = &events[0];
+    let expected = TraceValue::Error {
+        message: "startup failed".to_string(),
+        sources: vec![
+            "could not load config".to_string(),
+            "permission denied".to_string(),
+        ],
+    };
+    assert_eq!(event.fields.get("error"), Some(&expected));
+    assert_eq!(event.spans[0].fields.get("cause"), Some(&expected));
+
+    let formatted = event.format_full();
+    assert!(formatted.contains("error=startup failed"));
+    assert!(
+        formatted.contains(
+            "\n  cause caused by:\n    0: could not load config\n    1: permission denied"
+        )
+    );
+    assert!(
+        formatted.ends_with(
+            "\n  error caused by:\n    0: could not load config\n    1: permission denied"
+        )
+    );
+    assert_eq!(event.format_colored_multiline().len(), 7);
+    assert!(event.format_colored_full().contains("permission denied"));
+
+    let json = serde_json::to_value(&**event).unwrap();
+    assert_eq!(json["fields"]["error"]["message"], "startup failed");
+    assert_eq!(json["fields"]["error"]["sources"][1], "permission denied");
+}
+
+// Hand-written `valuable` impls, standing in for `#[derive(Valuable)]`
+struct Request {
+    method: &'static str,
+    retries: u32,
+    headers: std::collections::BTreeMap<String, String>,
+    tags: Vec<&'static str>,
+    outcome: Outcome,
+}
+
+enum Outcome {
+    Failed { status: u16 },
+}
+
+static REQUEST_FIELDS: &[NamedField<'static>] = &[
+    NamedField::new("method"),
+    NamedField::new("retries"),
+    NamedField::new("headers"),
+    NamedField::new("tags"),
+    NamedField::new("outcome"),
+];
+
+static FAILED_FIELDS: &[NamedField<'static>] = &[NamedField::new("status")];
+static OUTCOME_VARIANTS: &[VariantDef<'static>] =
+    &[VariantDef::new("Failed", Fields::Named(FAILED_FIELDS))];
+
+impl Valuable for Request {
+    fn as_value(&self) -> Value<'_> {
+        Value::Structable(self)
+    }
+
+    fn visit(&self, visit: &mut dyn Visit) {
+        visit.visit_named_fields(&NamedValues::new(
+            REQUEST_FIELDS,
+            &[
+                self.method.as_value(),
+                self.retries.as_value(),
+                self.headers.as_value(),
+                self.tags.as_value(),
+                self.outcome.as_value(),
+            ],
+        ));
+    }
+}
+
+impl Structable for Request {
+    fn definition(&self) -> StructDef<'_> {
+        StructDef::new_static("Request", Fields::Named(REQUEST_FIELDS))
+    }
+}
+
+impl Valuable for Outcome {
+    fn as_value(&self) -> Value<'_> {
+        Value::Enumerable(self)
+    }
+
+    fn visit(&self, visit: &mut dyn Visit) {
+        let Outcome::Failed { status } = self;
+        visit.visit_named_fields(&NamedValues::new(FAILED_FIELDS, &[status.as_value()]));
+    }
+}
+
+impl Enumerable for Outcome {
+    fn definition(&self) -> EnumDef<'_> {
+        EnumDef::new_static("Outcome", OUTCOME_VARIANTS)
+    }
+
+    fn variant(&self) -> Variant<'_> {
+        Variant::Static(&OUTCOME_VARIANTS[0])
+    }
+}
+
+fn sample_request() -> Request {
+    Request {
+        method: "GET",
+        retries: 2,
+        headers: [("host".to_string(), "example.com".to_string())].into(),
+        tags: vec!["edge", "cached"],
+        outcome: Outcome::Failed { status: 503 },
+    }
+}
+
+#[test]
+fn test_valuable_values_keep_structure() {
+    let value = TraceValue::from_valuable(sample_request().as_value());
+
+    let TraceValue::Struct { name, fields } = &value else {
+        panic!("Expected a struct, got {value:?}");
+    };
+    assert_eq!(name, "Request");
+    assert_eq!(fields.len(), 5);
+    assert_eq!(value.get("retries"), Some(&TraceValue::U64(2)));
+    assert_eq!(
+        value.get("headers").and_then(|headers| headers.get("host")),
+        Some(&TraceValue::String("example.com".into()))
+    );
+    assert_eq!(
+        value.get("tags"),
+        Some(&TraceValue::List(vec!["edge".into(), "cached".into()]))
+    );
+    assert_eq!(
+        value
+            .get("outcome")
+            .and_then(|outcome| outcome.get("status")),
+        Some(&TraceValue::U64(503))
+    );
+
+    // Rendered like Rust's own debug output
+    assert_eq!(
+        value.to_string(),
+        r#"Request { method: "GET", retries: 2, headers: {"host": "example.com"}, tags: ["edge", "cached"], outcome: Outcome::Failed { status: 503 } }"#
+    );
+
+    // Serialized the way serde would serialize the original types
+    assert_eq!(
+        serde_json::to_value(&value).unwrap(),
+        serde_json::json!({
+            "method": "GET",
+            "retries": 2,
+            "headers": { "host": "example.com" },
+            "tags": ["edge", "cached"],
+            "outcome": { "Failed": { "status": 503 } },
+        })
+    );
+
+    // Tuples and options flatten to lists and unit
+    let tuple = TraceValue::from_valuable((1i32, "two").as_value());
+    assert_eq!(tuple, TraceValue::List(vec![1i64.into(), "two".into()]));
+    assert_eq!(
+        TraceValue::from_valuable(None::<u8>.as_value()),
+        TraceValue::Unit
+    );
+}
+
+#[cfg(tracing_unstable)]
+#[test]
+fn test_valuable_fields_recorded_on_events_and_spans() {
+    let (subscriber, mut rx) = create_subscriber(SpanEvents::NONE);
+    let request = sample_request();
+
+    tracing::subscriber::with_default(subscriber, || {
+        let span = info_span!("handler", request = tracing::field::valuable(&request));
+        span.in_scope(|| {
+            info!(tags = tracing::field::valuable(&vec![1u8, 2, 3]), "handled");
+        });
+    });
+
+    let events = drain(&mut rx);
+    let event = &events[0];
+    assert_eq!(
+        event.fields.get("tags"),
+        Some(&TraceValue::List(vec![
+            TraceValue::U64(1),
+            TraceValue::U64(2),
+            TraceValue::U64(3)
+        ]))
+    );
+    let request = event.fields.get("request").unwrap();
+    assert!(matches!(request, TraceValue::Struct { name, .. } if name == "Request"));
+
+    let formatted = event.format_full();
+    assert!(formatted.contains("tags=[1, 2, 3]"));
+    assert!(formatted.contains("request=Request { method: \"GET\""));
+    assert!(
+        event
+            .format_colored_full()
+            .contains("Outcome::Failed { status: 503 }")
+    );
+
+    let json = serde_json::to_value(&**event).unwrap();
+    assert_eq!(json["fields"]["request"]["headers"]["host"], "example.com");
+    assert_eq!(json["fields"]["tags"], serde_json::json!([1, 2, 3]));
+}
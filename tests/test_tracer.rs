@@ -5,7 +5,8 @@ mod test_tracer {
     use std::{collections::HashMap, sync::Arc, time::Duration};
     use tokio::sync::Mutex;
     use tokio_tracer::{
-        Matcher, MatcherSet, TraceData, TraceEvent, TraceLevel, Tracer, TracerConfig, TracerTab,
+        BLOCK_TIMEOUT, Criterion, Matcher, MatcherSet, OverflowPolicy, TraceData, TraceEvent,
+        TraceLevel, Tracer, TracerConfig, TracerTab, Verdict,
     };
     use tracing::Level;
 
@@ -32,12 +33,17 @@ mod test_tracer {
             fields: HashMap::new(),
             span_name: span_name.map(|s| s.to_string()),
             span_hierarchy: span_name.map(|s| s.to_string()), // Initialize with same value as span_name
+            spans: Vec::new(),
+            span_event: None,
+            thread_name: None,
+            thread_id: 0,
+            task_id: None,
         };
 
         // Add some test fields
         event
             .fields
-            .insert("test_field".to_string(), "test_value".to_string());
+            .insert("test_field".to_string(), "test_value".into());
 
         Arc::new(event)
     }
@@ -67,12 +73,17 @@ mod test_tracer {
             fields: HashMap::new(),
             span_name: span_name.map(|s| s.to_string()),
             span_hierarchy: span_name.map(|s| s.to_string()),
+            spans: Vec::new(),
+            span_event: None,
+            thread_name: None,
+            thread_id: 0,
+            task_id: None,
         };
 
         // Add some test fields
         event
             .fields
-            .insert("test_field".to_string(), "test_value".to_string());
+            .insert("test_field".to_string(), "test_value".into());
 
         Arc::new(event)
     }
@@ -140,6 +151,7 @@ mod test_tracer {
                 TracerTab::new("tab_a".to_string()).with_matcher_set(matcher_set1.clone()),
                 TracerTab::new("tab_b".to_string()).with_matcher_set(matcher_set2.clone()),
             ],
+            ..TracerConfig::empty()
         };
 
         // Initialize tracer with the config
@@ -373,6 +385,7 @@ mod test_tracer {
                 TracerTab::new("lax_tab".to_string()).with_matcher_set(matcher_set1),
                 TracerTab::new("strict_tab".to_string()).with_matcher_set(matcher_set2),
             ],
+            ..TracerConfig::empty()
         };
 
         // Initialize tracer with the config
@@ -568,6 +581,7 @@ mod test_tracer {
                 TracerTab::new("normal_sub".to_string()).with_matcher_set(normal_matcher),
                 TracerTab::new("silencing_sub".to_string()).with_matcher_set(silencing_matcher),
             ],
+            ..TracerConfig::empty()
         };
 
         // Initialize tracer with the config
@@ -775,6 +789,7 @@ mod test_tracer {
             tabs: vec![
                 TracerTab::new("silencing_sub".to_string()).with_matcher_set(silencing_matcher),
             ],
+            ..TracerConfig::empty()
         };
 
         // Initialize tracer with the config
@@ -874,6 +889,7 @@ mod test_tracer {
         // Create config with the tab
         let config = TracerConfig {
             tabs: vec![TracerTab::new("test_tab".to_string()).with_matcher_set(matcher_set)],
+            ..TracerConfig::empty()
         };
 
         // Initialize tracer with the config
@@ -967,6 +983,7 @@ mod test_tracer {
         // Create config with the tab
         let config = TracerConfig {
             tabs: vec![TracerTab::new("test_tab".to_string()).with_matcher_set(matcher_set)],
+            ..TracerConfig::empty()
         };
 
         // Initialize tracer with the config
@@ -1108,6 +1125,7 @@ mod test_tracer {
         // Create config with the tab
         let config = TracerConfig {
             tabs: vec![TracerTab::new("level_test_tab".to_string()).with_matcher_set(matcher_set)],
+            ..TracerConfig::empty()
         };
 
         // Initialize tracer with the config
@@ -1274,6 +1292,7 @@ mod test_tracer {
         // Create config with the tab
         let config = TracerConfig {
             tabs: vec![TracerTab::new("test_tab".to_string()).with_matcher_set(matcher_set)],
+            ..TracerConfig::empty()
         };
 
         // Initialize tracer with the config
@@ -1399,6 +1418,7 @@ mod test_tracer {
                 TracerTab::new("span_tab".to_string()).with_matcher_set(span_matcher),
                 TracerTab::new("exclude_tab".to_string()).with_matcher_set(exclude_span_matcher),
             ],
+            ..TracerConfig::empty()
         };
 
         // Initialize tracer with the config
@@ -1563,6 +1583,7 @@ mod test_tracer {
                 TracerTab::new("exclude_target_tab".to_string())
                     .with_matcher_set(exclude_target_matcher),
             ],
+            ..TracerConfig::empty()
         };
 
         // Initialize tracer with the config
@@ -1699,6 +1720,7 @@ mod test_tracer {
             tabs: vec![
                 TracerTab::new("combined_tab".to_string()).with_matcher_set(combined_matcher),
             ],
+            ..TracerConfig::empty()
         };
 
         // Initialize tracer with the config
@@ -1824,6 +1846,7 @@ mod test_tracer {
                 TracerTab::new("multi_target_tab".to_string())
                     .with_matcher_set(multi_target_matcher),
             ],
+            ..TracerConfig::empty()
         };
 
         // Initialize tracer with the config
@@ -1930,6 +1953,7 @@ mod test_tracer {
             tabs: vec![
                 TracerTab::new("wildcard_tab".to_string()).with_matcher_set(wildcard_matcher),
             ],
+            ..TracerConfig::empty()
         };
 
         // Initialize tracer with the config
@@ -2050,6 +2074,7 @@ mod test_tracer {
             tabs: vec![
                 TracerTab::new("complex_target_tab".to_string()).with_matcher_set(complex_matcher),
             ],
+            ..TracerConfig::empty()
         };
 
         // Initialize tracer with the config
@@ -2174,6 +2199,7 @@ mod test_tracer {
             tabs: vec![
                 TracerTab::new("all_matchers_tab".to_string()).with_matcher_set(combined_matcher),
             ],
+            ..TracerConfig::empty()
         };
 
         // Initialize tracer with the config
@@ -2282,4 +2308,714 @@ mod test_tracer {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_callsites_prefiltered_by_tab_matchers() -> Result<()> {
+        use tokio_tracer::TracingSubscriber;
+        use tracing::{debug, info, level_filters::LevelFilter, warn};
+
+        let tracer = Tracer::new_with_config(
+            TracerConfig::empty().with_tab(
+                "app",
+                Matcher::info()
+                    .all_modules()
+                    .target_pattern("test_tracer::**"),
+            ),
+        );
+        let dispatch = tracing::Dispatch::new(tracer.subscriber());
+        let emit = || {
+            tracing::dispatcher::with_default(&dispatch, || {
+                debug!(target: "noisy_dep", "dependency debug");
+                warn!(target: "noisy_dep", "dependency warning");
+                info!("application info");
+            })
+        };
+        let max_level = || {
+            let subscriber = dispatch.downcast_ref::<TracingSubscriber>().unwrap();
+            tracing::Subscriber::max_level_hint(subscriber)
+        };
+
+        // Only the application event can be captured, so the others never reach the
+        // dispatcher. Spans of any level may hold captured events, so the hint stays open.
+        assert_eq!(max_level(), Some(LevelFilter::TRACE));
+        emit();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(tracer.get_captured_count(), 1);
+        assert_eq!(tracer.get_dropped_count(), 0);
+
+        // Adding a tab widens the interest of already registered callsites
+        tracer
+            .add_tab(
+                "deps",
+                Matcher::debug()
+                    .all_modules()
+                    .target_pattern("noisy_dep")
+                    .into_matcher_set(),
+            )?
+            .await??;
+        assert_eq!(max_level(), Some(LevelFilter::TRACE));
+        emit();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(tracer.get_captured_count(), 4);
+        assert_eq!(tracer.get_dropped_count(), 0);
+
+        // Removing it narrows it again
+        tracer.remove_tab("deps")?.await??;
+        assert_eq!(max_level(), Some(LevelFilter::TRACE));
+        emit();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(tracer.get_captured_count(), 5);
+        assert_eq!(tracer.get_dropped_count(), 0);
+
+        // A dropped callback needs to see everything
+        let dropped = Arc::new(Mutex::new(Vec::new()));
+        let dropped_clone = dropped.clone();
+        tracer
+            .set_dropped_callback(move |event| {
+                dropped_clone
+                    .try_lock()
+                    .unwrap()
+                    .push(event.message.clone());
+            })?
+            .await??;
+        assert_eq!(max_level(), Some(LevelFilter::TRACE));
+        emit();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(tracer.get_captured_count(), 6);
+        assert_eq!(tracer.get_dropped_count(), 2);
+        assert_eq!(
+            *dropped.lock().await,
+            vec![
+                "dependency debug".to_string(),
+                "dependency warning".to_string()
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_level_match_modes_reach_verbose_callsites() -> Result<()> {
+        use tracing::{debug, info, trace};
+
+        let (tracer, _guard) = Tracer::scoped(
+            TracerConfig::empty()
+                .with_tab("debug_only", Matcher::debug().all_modules().exactly())
+                .with_tab(
+                    "chatty",
+                    Matcher::range(Level::DEBUG, Level::TRACE).all_modules(),
+                ),
+        );
+        let captured = Arc::new(Mutex::new(Vec::new()));
+        let captured_clone = captured.clone();
+        tracer
+            .set_callback(move |event, tabs| {
+                let mut tabs: Vec<String> = tabs.iter().map(|tab| tab.to_string()).collect();
+                tabs.sort();
+                captured_clone
+                    .try_lock()
+                    .unwrap()
+                    .push((event.message.clone(), tabs));
+            })?
+            .await??;
+
+        // Trace callsites stay enabled since a tab can capture them
+        info!("info event");
+        debug!("debug event");
+        trace!("trace event");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        assert_eq!(
+            *captured.lock().await,
+            vec![
+                (
+                    "debug event".to_string(),
+                    vec!["chatty".to_string(), "debug_only".to_string()]
+                ),
+                ("trace event".to_string(), vec!["chatty".to_string()]),
+            ]
+        );
+        assert_eq!(tracer.get_dropped_count(), 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_scoped_tracers_coexist() -> Result<()> {
+        use tracing::{error, info, info_span, instrument::WithSubscriber};
+
+        let (errors, errors_guard) = Tracer::scoped(
+            TracerConfig::empty().with_tab("errors", Matcher::error().all_modules()),
+        );
+        error!("first error");
+
+        // The innermost scope wins on this thread until its guard is dropped
+        {
+            let (verbose, _verbose_guard) = Tracer::scoped(
+                TracerConfig::empty().with_tab("all", Matcher::trace().all_modules()),
+            );
+            info_span!("job").in_scope(|| {
+                info!("job info");
+                error!("job error");
+            });
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            assert_eq!(verbose.get_captured_count(), 2);
+        }
+
+        error!("second error");
+        info!("not an error");
+        drop(errors_guard);
+        error!("after the guard");
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(errors.get_captured_count(), 2);
+        assert_eq!(errors.get_dropped_count(), 0);
+
+        // A dispatch can follow a task onto any worker thread
+        let tasks = Tracer::new_with_config(
+            TracerConfig::empty().with_tab("tasks", Matcher::info().all_modules()),
+        );
+        tokio::spawn(
+            async {
+                info!("inside task");
+                tokio::task::yield_now().await;
+                info!("after yield");
+            }
+            .with_subscriber(tasks.dispatch()),
+        )
+        .await?;
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(tasks.get_captured_count(), 2);
+        assert_eq!(errors.get_captured_count(), 2);
+
+        Ok(())
+    }
+
+    // Helper to create a tracer with one tab capturing everything into a shared list
+    async fn create_bounded_tracer(
+        capacity: usize,
+        policy: OverflowPolicy,
+    ) -> Result<(Tracer, Arc<Mutex<Vec<String>>>)> {
+        let tracer = Tracer::new_with_config(
+            TracerConfig::empty()
+                .with_tab("all", Matcher::trace().all_modules())
+                .with_capacity(capacity)
+                .with_overflow_policy(policy),
+        );
+
+        let captured = Arc::new(Mutex::new(Vec::new()));
+        let captured_clone = captured.clone();
+        tracer
+            .set_callback(move |event, _tabs| {
+                captured_clone
+                    .try_lock()
+                    .unwrap()
+                    .push(event.message.clone());
+            })?
+            .await??;
+
+        Ok((tracer, captured))
+    }
+
+    #[tokio::test]
+    async fn test_bounded_queue_overflow_policies() -> Result<()> {
+        // The dispatcher only runs when we yield, so a burst fills the queue
+        let burst = [
+            (Level::ERROR, "error"),
+            (Level::TRACE, "trace"),
+            (Level::INFO, "info"),
+            (Level::DEBUG, "debug"),
+            (Level::WARN, "warn"),
+        ];
+        let send_burst = |tracer: &Tracer| {
+            let tx = tracer._get_sender_for_testing();
+            burst
+                .iter()
+                .enumerate()
+                .filter(|(id, (level, message))| {
+                    let event = create_test_event(
+                        *id as u64,
+                        *level,
+                        message,
+                        Some("app"),
+                        None,
+                        None,
+                        None,
+                    );
+                    tx.send(event).is_err()
+                })
+                .count()
+        };
+
+        let (tracer, captured) = create_bounded_tracer(3, OverflowPolicy::DropNewest).await?;
+        assert_eq!(send_burst(&tracer), 2, "The last two events are rejected");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(*captured.lock().await, vec!["error", "trace", "info"]);
+        assert_eq!(tracer.get_overflowed_count(), 2);
+
+        let (tracer, captured) = create_bounded_tracer(3, OverflowPolicy::DropOldest).await?;
+        assert_eq!(send_burst(&tracer), 0);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(*captured.lock().await, vec!["info", "debug", "warn"]);
+        assert_eq!(tracer.get_overflowed_count(), 2);
+
+        // Trace is evicted for debug, then debug for warn
+        let (tracer, captured) = create_bounded_tracer(3, OverflowPolicy::DropLowestLevel).await?;
+        assert_eq!(send_burst(&tracer), 0);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(*captured.lock().await, vec!["error", "info", "warn"]);
+        assert_eq!(tracer.get_overflowed_count(), 2);
+
+        tracer.clear_stats()?.await??;
+        assert_eq!(tracer.get_overflowed_count(), 0);
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_bounded_queue_block_policy() -> Result<()> {
+        let tracer = Tracer::new_with_config(
+            TracerConfig::empty()
+                .with_tab("all", Matcher::trace().all_modules())
+                .with_capacity(1)
+                .with_overflow_policy(OverflowPolicy::Block),
+        );
+        tracer
+            .set_callback(|_event, _tabs| std::thread::sleep(Duration::from_millis(5)))?
+            .await??;
+
+        // A slow consumer makes the emitting thread wait instead of losing events
+        let tx = tracer._get_sender_for_testing();
+        for id in 0..20 {
+            let event = create_test_event(id, Level::INFO, "burst", Some("app"), None, None, None);
+            tx.send(event).unwrap();
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(tracer.get_captured_count(), 20);
+        assert_eq!(tracer.get_overflowed_count(), 0);
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_bounded_queue_block_policy_from_worker_thread() -> Result<()> {
+        let tracer = Tracer::new_with_config(
+            TracerConfig::empty()
+                .with_tab("all", Matcher::trace().all_modules())
+                .with_capacity(1)
+                .with_overflow_policy(OverflowPolicy::Block),
+        );
+        tracer.set_callback(|_event, _tabs| {})?.await??;
+
+        // Waking the dispatcher from a worker puts it in that worker's LIFO slot, so the
+        // emitting task must hand the worker over while it waits for room
+        let dispatch = tracer.dispatch();
+        let emitter = tokio::spawn(async move {
+            tracing::dispatcher::with_default(&dispatch, || {
+                for id in 0..20 {
+                    tracing::info!(id, "burst");
+                }
+            })
+        });
+        tokio::time::timeout(Duration::from_secs(5), emitter)
+            .await
+            .expect("emitting task blocked the dispatcher")?;
+
+        tracer.flush()?.await??;
+        assert_eq!(tracer.get_captured_count(), 20);
+        assert_eq!(tracer.get_overflowed_count(), 0);
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_bounded_queue_block_policy_from_callback() -> Result<()> {
+        let tracer = Tracer::new_with_config(
+            TracerConfig::empty()
+                .with_tab("all", Matcher::trace().all_modules())
+                .with_capacity(1)
+                .with_overflow_policy(OverflowPolicy::Block),
+        );
+
+        // Only the dispatcher drains the queue, so a callback that logs into a full
+        // queue drops the newest event instead of waiting on itself
+        let dispatch = tracer.dispatch();
+        tracer
+            .set_callback(move |event, _tabs| {
+                if event.message == "burst" {
+                    tracing::dispatcher::with_default(&dispatch, || {
+                        for id in 0..3 {
+                            tracing::info!(id, "echo");
+                        }
+                    });
+                }
+            })?
+            .await??;
+
+        let tx = tracer._get_sender_for_testing();
+        tx.send(create_test_event(
+            1,
+            Level::INFO,
+            "burst",
+            Some("app"),
+            None,
+            None,
+            None,
+        ))
+        .unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async { tracer.flush()?.await? })
+            .await
+            .expect("callback blocked the dispatcher")?;
+        assert!(tracer.get_overflowed_count() >= 1);
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_bounded_queue_block_policy_gives_up_on_stalled_dispatcher() -> Result<()> {
+        let tracer = Tracer::new_with_config(
+            TracerConfig::empty()
+                .with_tab("all", Matcher::trace().all_modules())
+                .with_capacity(1)
+                .with_overflow_policy(OverflowPolicy::Block),
+        );
+
+        // The callback waits on a lock the emitting thread holds until it's done
+        let gate = Arc::new(std::sync::Mutex::new(()));
+        let gate_clone = gate.clone();
+        tracer
+            .set_callback(move |_event, _tabs| drop(gate_clone.lock().unwrap()))?
+            .await??;
+
+        let tx = tracer._get_sender_for_testing();
+        let emitter = std::thread::spawn(move || {
+            let _held = gate.lock().unwrap();
+            let started = std::time::Instant::now();
+            for id in 0..5 {
+                let event =
+                    create_test_event(id, Level::INFO, "burst", Some("app"), None, None, None);
+                let _ = tx.send(event);
+            }
+            started.elapsed()
+        });
+        let elapsed = tokio::task::spawn_blocking(move || emitter.join().unwrap()).await?;
+
+        // One wait runs out, after which the stalled queue drops without waiting
+        assert!(elapsed < BLOCK_TIMEOUT * 2, "waited {elapsed:?}");
+        tracer.flush()?.await??;
+        let overflowed = tracer.get_overflowed_count();
+        assert!(overflowed >= 1);
+        assert_eq!(tracer.get_captured_count() + overflowed, 5);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pending_events_bounded_until_callback() -> Result<()> {
+        let tracer = Tracer::new_with_config(
+            TracerConfig::empty()
+                .with_tab("all", Matcher::trace().all_modules())
+                .with_capacity(2)
+                .with_overflow_policy(OverflowPolicy::DropOldest),
+        );
+
+        // Without a callback captured events pile up in the dispatcher, within capacity
+        for id in 0..5 {
+            let message = format!("event {id}");
+            send_event(
+                &tracer,
+                create_test_event(id, Level::INFO, &message, Some("app"), None, None, None),
+            )
+            .await;
+        }
+        assert_eq!(tracer.get_captured_count(), 5);
+        assert_eq!(tracer.get_overflowed_count(), 3);
+
+        let captured = Arc::new(Mutex::new(Vec::new()));
+        let captured_clone = captured.clone();
+        tracer
+            .set_callback(move |event, _tabs| {
+                captured_clone
+                    .try_lock()
+                    .unwrap()
+                    .push(event.message.clone());
+            })?
+            .await??;
+        assert_eq!(*captured.lock().await, vec!["event 3", "event 4"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_message_patterns_capture_and_silence() -> Result<()> {
+        let tracer = Tracer::new_with_config(
+            TracerConfig::empty()
+                .with_tab(
+                    "timeouts",
+                    Matcher::debug().all_modules().message_contains("timeout"),
+                )
+                .with_tab(
+                    "app",
+                    MatcherSet::from_matchers([
+                        Matcher::debug().all_modules(),
+                        // Silence the known noisy heartbeat messages
+                        Matcher::trace()
+                            .all_modules()
+                            .message_regex(r"^heartbeat #\d+$")
+                            .exclude(),
+                    ]),
+                ),
+        );
+
+        let captured = Arc::new(Mutex::new(Vec::new()));
+        let captured_clone = captured.clone();
+        tracer
+            .set_callback(move |event, tabs| {
+                let tabs: Vec<String> = tabs.iter().map(|tab| tab.to_string()).collect();
+                captured_clone
+                    .try_lock()
+                    .unwrap()
+                    .push((event.message.clone(), tabs));
+            })?
+            .await??;
+
+        let silenced = Arc::new(Mutex::new(Vec::new()));
+        let silenced_clone = silenced.clone();
+        tracer
+            .set_silenced_callback(move |event, _silencers| {
+                silenced_clone
+                    .try_lock()
+                    .unwrap()
+                    .push(event.message.clone());
+            })?
+            .await??;
+
+        for (id, message) in [
+            "heartbeat #1",
+            "upstream timeout after 5s",
+            "request served",
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            send_event(
+                &tracer,
+                create_test_event(
+                    id as u64,
+                    Level::INFO,
+                    message,
+                    Some("app"),
+                    None,
+                    None,
+                    None,
+                ),
+            )
+            .await;
+        }
+
+        let mut captured = captured.lock().await.clone();
+        for (_, tabs) in captured.iter_mut() {
+            tabs.sort();
+        }
+        assert_eq!(
+            captured,
+            vec![
+                (
+                    "upstream timeout after 5s".to_string(),
+                    vec!["app".to_string(), "timeouts".to_string()]
+                ),
+                ("request served".to_string(), vec!["app".to_string()]),
+            ]
+        );
+        assert_eq!(*silenced.lock().await, vec!["heartbeat #1"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_first_match_tab_lets_earlier_rules_win() -> Result<()> {
+        let tracer = Tracer::new_with_config(
+            TracerConfig::empty().with_tab(
+                "db",
+                MatcherSet::from_matchers([
+                    Matcher::trace().module_pattern("db::slow_query"),
+                    Matcher::trace().module_pattern("db::*").exclude(),
+                    Matcher::info().all_modules(),
+                ])
+                .first_match(),
+            ),
+        );
+
+        let captured = Arc::new(Mutex::new(Vec::new()));
+        let captured_clone = captured.clone();
+        tracer
+            .set_callback(move |event, _tabs| {
+                captured_clone.try_lock().unwrap().push(event.id);
+            })?
+            .await??;
+
+        let silenced = Arc::new(Mutex::new(Vec::new()));
+        let silenced_clone = silenced.clone();
+        tracer
+            .set_silenced_callback(move |event, _silencers| {
+                silenced_clone.try_lock().unwrap().push(event.id);
+            })?
+            .await??;
+
+        for (id, level, module) in [
+            (1, Level::TRACE, "db::slow_query"),
+            (2, Level::WARN, "db::pool"),
+            (3, Level::INFO, "api"),
+            (4, Level::DEBUG, "api"),
+        ] {
+            send_event(
+                &tracer,
+                create_test_event(id, level, "query", Some(module), None, None, None),
+            )
+            .await;
+        }
+
+        assert_eq!(*captured.lock().await, vec![1, 3]);
+        assert_eq!(*silenced.lock().await, vec![2]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_explain_reports_every_tab() -> Result<()> {
+        let tracer = Tracer::new_with_config(
+            TracerConfig::empty()
+                .with_tab("api", Matcher::info().module_pattern("api::*"))
+                .with_tab(
+                    "quiet",
+                    MatcherSet::from_matchers([
+                        Matcher::trace().all_modules(),
+                        Matcher::trace().module_pattern("api::health").exclude(),
+                    ]),
+                )
+                .with_tab("errors", Matcher::error().all_modules()),
+        );
+
+        let event = create_test_event(
+            1,
+            Level::INFO,
+            "ping",
+            Some("api::health"),
+            None,
+            None,
+            None,
+        );
+        let explanations = tracer.explain(&event)?.await?;
+
+        assert_eq!(explanations.len(), 3);
+        assert_eq!(explanations["api"].verdict, Verdict::Captured);
+        assert_eq!(explanations["quiet"].verdict, Verdict::Silenced);
+        assert_eq!(explanations["errors"].verdict, Verdict::Dropped);
+        assert_eq!(
+            explanations["errors"].matchers[0].failed,
+            Some(Criterion::Level)
+        );
+
+        // Explaining an event neither dispatches nor counts it
+        tracer.flush()?.await??;
+        assert_eq!(tracer.get_captured_count(), 0);
+        assert_eq!(tracer.get_silenced_count(), 0);
+        assert_eq!(tracer.get_dropped_count(), 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_invalid_tabs_rejected() -> Result<()> {
+        let tracer = Tracer::new_with_config(
+            TracerConfig::empty().with_tab("app", Matcher::info().all_modules()),
+        );
+        let invalid = MatcherSet::from_matcher(Matcher::info().target_pattern("re:(").exclude());
+
+        let error = tracer
+            .add_tab("broken", invalid.clone())?
+            .await?
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Tab 'broken' has invalid target pattern `re:(`: invalid regex, unclosed group"
+        );
+
+        let error = tracer.update_tab("app", invalid)?.await?.unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("Tab 'app' has invalid target")
+        );
+
+        // Rejected sets leave the tabs as they were
+        let captured = Arc::new(Mutex::new(Vec::new()));
+        let captured_clone = captured.clone();
+        tracer
+            .set_callback(move |_event, tabs| {
+                let tabs: Vec<String> = tabs.iter().map(|tab| tab.to_string()).collect();
+                captured_clone.try_lock().unwrap().push(tabs);
+            })?
+            .await??;
+        send_event(
+            &tracer,
+            create_test_event(1, Level::INFO, "kept", Some("app"), None, None, None),
+        )
+        .await;
+        assert_eq!(*captured.lock().await, vec![vec!["app".to_string()]]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_verbose_spans_kept_for_less_verbose_tabs() -> Result<()> {
+        let (tracer, _guard) = Tracer::scoped(
+            TracerConfig::empty()
+                .with_tab("db", Matcher::info().all_modules().span_pattern("db_query"))
+                .with_tab("app", Matcher::debug().all_modules()),
+        );
+
+        let captured = Arc::new(Mutex::new(Vec::new()));
+        let captured_clone = captured.clone();
+        tracer
+            .set_callback(move |event, tabs| {
+                let mut tabs: Vec<String> = tabs.iter().map(|tab| tab.to_string()).collect();
+                tabs.sort();
+                captured_clone
+                    .try_lock()
+                    .unwrap()
+                    .push((event.span_name.clone(), tabs));
+            })?
+            .await??;
+
+        // The span is more verbose than any tab, but the event inside it is not
+        tracing::trace_span!("db_query").in_scope(|| tracing::info!("slow query"));
+        tracing::trace!("too verbose");
+        tracer.flush()?.await??;
+
+        assert_eq!(
+            *captured.lock().await,
+            vec![(
+                Some("db_query".to_string()),
+                vec!["app".to_string(), "db".to_string()]
+            )]
+        );
+        assert_eq!(tracer.get_dropped_count(), 0);
+
+        // Without tabs nothing is needed, spans included
+        tracer.remove_tab("db")?.await??;
+        tracer.remove_tab("app")?.await??;
+        let dispatch = tracer.dispatch();
+        let subscriber = dispatch
+            .downcast_ref::<tokio_tracer::TracingSubscriber>()
+            .unwrap();
+        assert_eq!(
+            tracing::Subscriber::max_level_hint(subscriber),
+            Some(tracing::level_filters::LevelFilter::OFF)
+        );
+
+        Ok(())
+    }
 }